@@ -0,0 +1,8 @@
+#![no_main]
+
+use haversine_calculator::hvb::deserialize_hvb;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = deserialize_hvb(data);
+});
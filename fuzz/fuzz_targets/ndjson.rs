@@ -0,0 +1,8 @@
+#![no_main]
+
+use haversine_calculator::parser::deserialize_ndjson;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = deserialize_ndjson(&mut &data[..]);
+});
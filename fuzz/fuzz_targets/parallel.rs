@@ -0,0 +1,27 @@
+#![no_main]
+
+use haversine_calculator::parser::{deserialize_parallel, deserialize_single_pass, Format};
+use libfuzzer_sys::fuzz_target;
+
+// The first byte picks the thread count, so records get split at different points
+fuzz_target!(|data: &[u8]| {
+    let Some((&threads, input)) = data.split_first() else {
+        return;
+    };
+    let Ok(input) = std::str::from_utf8(input) else {
+        return;
+    };
+    let parallel = deserialize_parallel(input, threads as usize % 8 + 1);
+    let single_pass = deserialize_single_pass(input);
+    match (parallel, single_pass) {
+        (Ok(parallel), Ok(single_pass)) => {
+            let bits = |pairs: Vec<_>| {
+                let mut out = vec![];
+                Format::Binary.write_pairs(pairs.into_iter(), &mut out).unwrap();
+                out
+            };
+            assert_eq!(bits(parallel), bits(single_pass));
+        }
+        (parallel, single_pass) => assert_eq!(parallel.is_ok(), single_pass.is_ok()),
+    }
+});
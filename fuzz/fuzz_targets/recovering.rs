@@ -0,0 +1,15 @@
+#![no_main]
+
+use haversine_calculator::{
+    parser::{ArrayIter, ParseOptions},
+    CoordPair,
+};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    // A short cap so the fuzzer reaches records that run too long
+    let mut pairs = ArrayIter::<_, CoordPair>::new(data, ParseOptions::default())
+        .recovering(4)
+        .with_max_record_len(64);
+    for _ in &mut pairs {}
+});
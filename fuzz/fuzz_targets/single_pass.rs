@@ -0,0 +1,10 @@
+#![no_main]
+
+use haversine_calculator::parser::deserialize_single_pass;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(input) = std::str::from_utf8(data) {
+        let _ = deserialize_single_pass(input);
+    }
+});
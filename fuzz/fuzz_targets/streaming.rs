@@ -0,0 +1,8 @@
+#![no_main]
+
+use haversine_calculator::{parser::deserialize, CoordPair};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = deserialize::<Vec<CoordPair>>(&mut &data[..]);
+});
@@ -0,0 +1,9 @@
+#![no_main]
+
+use haversine_calculator::structural::{deserialize_structural, StructuralIndex};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    assert_eq!(StructuralIndex::new(data), StructuralIndex::scalar(data));
+    let _ = deserialize_structural(data);
+});
@@ -0,0 +1,210 @@
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use syn::{
+    parse_macro_input, parse_quote, Data, DeriveInput, Fields, GenericParam, Generics, LitByteStr,
+    LitStr,
+};
+
+// Derives haversine_calculator's streaming `Serializable` and `Deserializable` for structs with named fields.
+// Each struct is one JSON object with a member per field, in declaration order when writing and in any
+// order when reading. Members the struct doesn't have are skipped and a repeated one overwrites the
+// earlier value, unless the tokenizer parses strictly. Missing members are always an error.
+// `#[json(rename = "name")]` on a field sets its key
+
+#[proc_macro_derive(Serializable, attributes(json))]
+pub fn derive_serializable(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_serializable(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+#[proc_macro_derive(Deserializable, attributes(json))]
+pub fn derive_deserializable(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_deserializable(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+struct Field {
+    ident: syn::Ident,
+    ty: syn::Type,
+    /// The key as it's spelled in the JSON, escapes included
+    key: String,
+}
+
+fn fields(input: &DeriveInput) -> syn::Result<Vec<Field>> {
+    let Data::Struct(data) = &input.data else {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            "only structs can derive Serializable and Deserializable",
+        ));
+    };
+    let Fields::Named(named) = &data.fields else {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            "only structs with named fields can derive Serializable and Deserializable",
+        ));
+    };
+    named
+        .named
+        .iter()
+        .map(|field| {
+            let ident = field.ident.clone().unwrap();
+            let mut name = ident.to_string().trim_start_matches("r#").to_string();
+            for attr in field.attrs.iter().filter(|attr| attr.path().is_ident("json")) {
+                attr.parse_nested_meta(|meta| {
+                    if meta.path.is_ident("rename") {
+                        name = meta.value()?.parse::<LitStr>()?.value();
+                        Ok(())
+                    } else {
+                        Err(meta.error("unsupported json attribute, expected `rename`"))
+                    }
+                })?;
+            }
+            Ok(Field {
+                ident,
+                ty: field.ty.clone(),
+                key: escape(&name),
+            })
+        })
+        .collect()
+}
+
+fn escape(key: &str) -> String {
+    let mut out = String::with_capacity(key.len());
+    for c in key.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Every type parameter has to implement the trait too
+fn bounded(generics: &Generics, bound: TokenStream2) -> Generics {
+    let mut generics = generics.clone();
+    for param in &mut generics.params {
+        if let GenericParam::Type(param) = param {
+            param.bounds.push(parse_quote!(#bound));
+        }
+    }
+    generics
+}
+
+fn expand_serializable(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let fields = fields(&input)?;
+    let name = &input.ident;
+    let generics = bounded(
+        &input.generics,
+        quote!(::haversine_calculator::parser::Serializable),
+    );
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let writes = fields.iter().enumerate().map(|(i, field)| {
+        let separator = if i == 0 { "{" } else { "," };
+        let prefix = LitByteStr::new(
+            format!("{separator}\"{}\":", field.key).as_bytes(),
+            field.ident.span(),
+        );
+        let ident = &field.ident;
+        quote! {
+            writer.write_all(#prefix)?;
+            ::haversine_calculator::parser::Serializable::streaming_serialize(&mut self.#ident, writer)?;
+        }
+    });
+    let close: &[u8] = if fields.is_empty() { b"{}" } else { b"}" };
+    let close = LitByteStr::new(close, name.span());
+    Ok(quote! {
+        impl #impl_generics ::haversine_calculator::parser::Serializable for #name #ty_generics #where_clause {
+            fn streaming_serialize(
+                &mut self,
+                writer: &mut impl ::std::io::Write,
+            ) -> ::std::result::Result<(), ::std::io::Error> {
+                #(#writes)*
+                writer.write_all(#close)
+            }
+        }
+    })
+}
+
+fn expand_deserializable(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let fields = fields(&input)?;
+    let name = &input.ident;
+    let generics = bounded(
+        &input.generics,
+        quote!(::haversine_calculator::parser::Deserializable),
+    );
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let record = LitStr::new(&name.to_string(), name.span());
+    let keys = fields
+        .iter()
+        .map(|field| LitByteStr::new(field.key.as_bytes(), field.ident.span()));
+    let slots: Vec<_> = (0..fields.len())
+        .map(|i| format_ident!("__field{}", i))
+        .collect();
+    let declarations = fields.iter().zip(&slots).map(|(field, slot)| {
+        let ty = &field.ty;
+        quote!(let mut #slot: ::std::option::Option<#ty> = ::std::option::Option::None;)
+    });
+    let reads = fields.iter().zip(&slots).enumerate().map(|(i, (field, slot))| {
+        let ty = &field.ty;
+        let member = LitStr::new(&field.key, field.ident.span());
+        quote! {
+            ::haversine_calculator::tokenizer::Member::Known(#i) => {
+                if #slot.is_some() && __reader.options().strict {
+                    return ::std::result::Result::Err(__reader.duplicate_member(#member));
+                }
+                #slot = ::std::option::Option::Some(
+                    <#ty as ::haversine_calculator::parser::Deserializable>::tokenized_deserialize(__reader)?,
+                );
+            }
+        }
+    });
+    let assignments = fields.iter().zip(&slots).map(|(field, slot)| {
+        let ident = &field.ident;
+        let member = LitStr::new(&field.key, field.ident.span());
+        quote! {
+            #ident: match #slot {
+                ::std::option::Option::Some(val) => val,
+                ::std::option::Option::None => {
+                    return ::std::result::Result::Err(__reader.locate(
+                        ::haversine_calculator::parser::DeserializationError::missing_member(#record, #member),
+                    ));
+                }
+            },
+        }
+    });
+    Ok(quote! {
+        impl #impl_generics ::haversine_calculator::parser::Deserializable for #name #ty_generics #where_clause {
+            fn streaming_deserialize(
+                reader: &mut impl ::std::io::BufRead,
+            ) -> ::std::result::Result<Self, ::haversine_calculator::parser::DeserializationError> {
+                Self::tokenized_deserialize(&mut ::haversine_calculator::tokenizer::Tokenizer::new(reader))
+            }
+
+            fn tokenized_deserialize<__R: ::std::io::BufRead>(
+                __reader: &mut ::haversine_calculator::tokenizer::Tokenizer<__R>,
+            ) -> ::std::result::Result<Self, ::haversine_calculator::parser::DeserializationError> {
+                const KEYS: &[&[u8]] = &[#(#keys),*];
+                #(#declarations)*
+                __reader.expect(b'{', "Expected '{'")?;
+                let mut __first = true;
+                loop {
+                    match __reader.next_member(KEYS, &mut __first)? {
+                        ::haversine_calculator::tokenizer::Member::End => break,
+                        #(#reads)*
+                        _ => __reader.skip_value()?,
+                    }
+                }
+                ::std::result::Result::Ok(Self {
+                    #(#assignments)*
+                })
+            }
+        }
+    })
+}
@@ -1,17 +1,35 @@
 use std::{
+    cell::Cell,
     fs::File,
-    io::{self, BufReader, BufWriter},
-    path::PathBuf,
+    io::{self, BufRead, BufReader, BufWriter, Read, Write},
+    path::{Path, PathBuf},
+    rc::Rc,
+    time::{Duration, Instant},
 };
 
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand, ValueEnum};
 use haversine_calculator::{
+    bench_block,
     calc::naive_haversine,
-    generate::CoordPairGen,
-    parser::{deserialize, serialize},
+    generate::{ClusterShape, Concentration, CoordPairGen},
+    hvb::{hvb_count_for_len, HvbFile},
+    input::{Advice, InputSource, Mmap},
+    messy::MessyJson,
+    metadata::{Checksum, ChecksumReader, ChecksumWriter, GenerationMetadata},
+    metrics::{record_bytes, Benchmark},
+    parser::{
+        deserialize_ndjson_recovering, deserialize_parallel_with, deserialize_single_pass_with,
+        deserialize_tree_with, looks_like_envelope, ArrayIter, DeserializationError, Format,
+        ParseOptions, ParseStats, Skipped,
+    },
+    reference::AnswerChecker,
+    region::{BoundingBox, Polygon, Region},
+    rng::Xoshiro256StarStar,
+    structural::deserialize_structural_with,
+    track::{write_tracks_json, TrackGen, TrackParams},
     CoordPair,
 };
-use rand::{rngs::StdRng, SeedableRng};
+use rand::SeedableRng;
 
 #[derive(Parser)]
 #[command(version, about, long_about = None)]
@@ -23,46 +41,661 @@ struct Cli {
     command: Commands,
 }
 
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Mode {
+    /// Independent random pairs
+    Pairs,
+    /// Consecutive points of random walk tracks as pairs
+    TrackSegments,
+    /// Random walk tracks as arrays of points (json only)
+    TrackPoints,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum JsonParser {
+    /// Streams records straight from the file
+    Streaming,
+    /// Parses the whole document into a tree first, accepts envelopes like {"pairs": [...]}
+    Tree,
+    /// Reads the whole document in one pass over its bytes, in any layout, but only with number values
+    SinglePass,
+    /// Builds a SIMD index of the structural characters first, then reads the numbers between them
+    Structural,
+    /// Single-pass split into chunks at record boundaries and parsed on several threads
+    Parallel,
+}
+
+#[derive(Args)]
+struct GenerateArgs {
+    #[arg(short, long, default_value_t = 10_000)]
+    count: usize,
+    /// Keep generating until the output reaches this many bytes, instead of a fixed count
+    #[arg(long, conflicts_with = "count")]
+    target_bytes: Option<u64>,
+    #[arg(short, long, default_value_t = 1212121212)]
+    seed: u64,
+    #[arg(short, long, default_value_t = false)]
+    uniform: bool,
+    #[arg(short, long, value_enum, default_value_t = Format::Json)]
+    format: Format,
+    /// Randomize whitespace, key order and number spelling (json only)
+    #[arg(long, default_value_t = false)]
+    messy: bool,
+    /// Add unknown members to each pair object
+    #[arg(long, default_value_t = false, requires = "messy")]
+    extra_keys: bool,
+    #[arg(short, long, value_enum, default_value_t = Mode::Pairs)]
+    mode: Mode,
+    #[arg(long, value_enum, default_value_t = ClusterShape::Box)]
+    cluster_shape: ClusterShape,
+    /// Concentration of gaussian and von Mises-Fisher clusters, higher is tighter
+    #[arg(long, default_value = "100")]
+    concentration: Concentration,
+    #[command(flatten)]
+    track: TrackParams,
+    /// Only generate points inside `min_lat,min_lon,max_lat,max_lon`. min_lon > max_lon crosses the antimeridian
+    #[arg(long, allow_hyphen_values = true, conflicts_with = "polygon")]
+    bbox: Option<BoundingBox>,
+    /// Only generate points inside the polygon `lat,lon;lat,lon;...`
+    #[arg(long, allow_hyphen_values = true)]
+    polygon: Option<Polygon>,
+}
+
 #[derive(Subcommand)]
 enum Commands {
-    Generate {
-        #[arg(short, long, default_value_t = 10_000)]
-        count: usize,
-        #[arg(short, long, default_value_t = 1212121212)]
-        seed: u64,
-        #[arg(short, long, default_value_t = false)]
-        uniform: bool,
-    },
-    Calculate {},
+    /// Generate input data. Pass `-` as FILE to write to stdout
+    Generate(GenerateArgs),
+    Calculate(CalculateArgs),
+}
+
+#[derive(Args)]
+struct CalculateArgs {
+    /// Detected from the start of the file when not given
+    #[arg(short, long, value_enum)]
+    format: Option<Format>,
+    /// Parser used for the json format, streaming unless the file is an envelope like {"pairs": [...]}
+    #[arg(short, long, value_enum)]
+    parser: Option<JsonParser>,
+    /// Threads for the parallel parser, defaults to the number of cores
+    #[arg(long)]
+    threads: Option<usize>,
+    /// Reject unknown and repeated members and values that aren't plain numbers (json, ndjson and reference only)
+    #[arg(long, default_value_t = false)]
+    strict: bool,
+    #[arg(short, long, value_enum, default_value_t = InputSource::Read)]
+    input: InputSource,
+    /// madvise hints for the mapping, comma separated (mmap input only)
+    #[arg(long, value_enum, value_delimiter = ',')]
+    madvise: Vec<Advice>,
+    /// Don't check the input against its `.meta` sidecar, or an `.hvb` file against its checksum
+    #[arg(long, default_value_t = false)]
+    no_verify: bool,
+    /// Print the profiler's timings when done
+    #[arg(long, default_value_t = false)]
+    profile: bool,
+    /// Check the distances and their average against a `.f64` answer file, like the course's reference ones
+    #[arg(long, value_name = "FILE")]
+    answers: Option<PathBuf>,
+    /// Print record counts and sizes, whitespace and skipped keys (streaming json with read input only)
+    #[arg(long, default_value_t = false)]
+    stats: bool,
+    /// Skip records that don't parse instead of stopping at the first, and fail only if more than this many
+    /// are skipped (streaming json with read input, and ndjson)
+    #[arg(long, value_name = "COUNT")]
+    max_skipped: Option<u64>,
+}
+
+impl CalculateArgs {
+    fn format(&self) -> Format {
+        self.format.unwrap_or(Format::Json)
+    }
+
+    fn parser(&self) -> JsonParser {
+        self.parser.unwrap_or(JsonParser::Streaming)
+    }
+
+    fn parse_options(&self) -> ParseOptions {
+        ParseOptions {
+            strict: self.strict,
+        }
+    }
+}
+
+fn value_name(val: impl ValueEnum) -> String {
+    val.to_possible_value().unwrap().get_name().to_string()
+}
+
+fn invalid_input(msg: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, msg.into())
+}
+
+// Shared between the output writer, which counts bytes, and the item iterator,
+// which stops at the target size and reports throughput on stderr
+struct ProgressState {
+    bytes: Cell<u64>,
+    items: Cell<usize>,
+    target_bytes: Option<u64>,
+    start: Instant,
+    last_report: Cell<Instant>,
+}
+
+#[derive(Clone)]
+struct Progress(Rc<ProgressState>);
+
+impl Progress {
+    fn new(target_bytes: Option<u64>) -> Self {
+        Self(Rc::new(ProgressState {
+            bytes: Cell::new(0),
+            items: Cell::new(0),
+            target_bytes,
+            start: Instant::now(),
+            last_report: Cell::new(Instant::now()),
+        }))
+    }
+
+    fn track<I: Iterator>(&self, items: I) -> Tracked<I> {
+        Tracked {
+            items,
+            progress: self.clone(),
+        }
+    }
+
+    fn target_reached(&self) -> bool {
+        let state = &self.0;
+        state
+            .target_bytes
+            .is_some_and(|target| state.bytes.get() >= target)
+    }
+
+    fn item(&self) {
+        let state = &self.0;
+        state.items.set(state.items.get() + 1);
+        if state.items.get().is_multiple_of(4096)
+            && state.last_report.get().elapsed() > Duration::from_millis(500)
+        {
+            state.last_report.set(Instant::now());
+            self.report("\r");
+        }
+    }
+
+    fn report(&self, end: &str) {
+        let state = &self.0;
+        let mb = state.bytes.get() as f64 / 1_000_000.0;
+        let secs = state.start.elapsed().as_secs_f64();
+        eprint!(
+            "{} items, {:.2}MB written ({:.2}MB/s){}",
+            state.items.get(),
+            mb,
+            mb / secs,
+            end
+        );
+    }
+}
+
+/// Counts items as they go by, and stops once enough bytes are written. The budget is checked before
+/// asking for the next item, so the generator isn't run past the last one that's written
+struct Tracked<I> {
+    items: I,
+    progress: Progress,
+}
+
+impl<I: Iterator> Iterator for Tracked<I> {
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.progress.target_reached() {
+            return None;
+        }
+        let item = self.items.next()?;
+        self.progress.item();
+        Some(item)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self.progress.0.target_bytes {
+            // Could stop at any point
+            Some(_) => (0, self.items.size_hint().1),
+            None => self.items.size_hint(),
+        }
+    }
+}
+
+struct ProgressWriter<W> {
+    inner: W,
+    progress: Progress,
+}
+
+impl<W: Write> Write for ProgressWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        let bytes = &self.progress.0.bytes;
+        bytes.set(bytes.get() + written as u64);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+fn write_pairs(
+    args: &GenerateArgs,
+    pairs: impl Iterator<Item = CoordPair>,
+    writer: &mut impl Write,
+) -> Result<(), io::Error> {
+    if args.messy {
+        // Separate stream so the values match a tidy file generated with the same seed
+        let layout_rng = Xoshiro256StarStar::seed_from_u64(!args.seed);
+        MessyJson::new(layout_rng, args.extra_keys).write_pairs(pairs, writer)
+    } else {
+        args.format.write_pairs(pairs, writer)
+    }
+}
+
+fn generate(path: &Path, args: GenerateArgs) -> Result<(), io::Error> {
+    if (args.messy || args.mode == Mode::TrackPoints) && args.format != Format::Json {
+        return Err(invalid_input(
+            "--messy and track points are only supported for the json format",
+        ));
+    }
+
+    let to_stdout = path == Path::new("-");
+    let (count, target_bytes) = match (args.target_bytes, args.format) {
+        // The count goes in the header, so it's worked out from the size up front
+        (Some(target), Format::Hvb | Format::HvbColumns) => (hvb_count_for_len(target), None),
+        (Some(target), _) => (usize::MAX, Some(target)),
+        (None, _) => (args.count, None),
+    };
+    let output: Box<dyn Write> = if to_stdout {
+        Box::new(io::stdout().lock())
+    } else {
+        Box::new(File::create(path)?)
+    };
+    let progress = Progress::new(target_bytes);
+    let mut writer = ChecksumWriter::new(ProgressWriter {
+        inner: BufWriter::new(output),
+        progress: progress.clone(),
+    });
+
+    let mut metadata = GenerationMetadata::new(args.seed, args.count, &value_name(args.mode));
+    metadata.param("format", value_name(args.format));
+    metadata.param("messy", args.messy);
+    metadata.param("extra_keys", args.extra_keys);
+    match args.mode {
+        Mode::Pairs => {
+            metadata.param("clustered", !args.uniform);
+            metadata.param("cluster_shape", value_name(args.cluster_shape));
+            metadata.param("concentration", args.concentration);
+            let mut generator = CoordPairGen::from_seed(args.seed, !args.uniform, count)
+                .with_cluster_shape(args.cluster_shape, args.concentration);
+            if let Some(bbox) = args.bbox {
+                metadata.param("bbox", bbox);
+                generator = generator.with_region(Region::BoundingBox(bbox));
+            }
+            if let Some(polygon) = &args.polygon {
+                metadata.param("polygon", polygon);
+                generator = generator.with_region(Region::Polygon(polygon.clone()));
+            }
+            write_pairs(&args, progress.track(&mut generator), &mut writer)?;
+            metadata.record_clusters(generator.clusters());
+        }
+        Mode::TrackSegments | Mode::TrackPoints => {
+            metadata.param("speed", args.track.speed);
+            metadata.param("heading_drift", args.track.heading_drift);
+            metadata.param("interval", args.track.interval);
+            metadata.param("gps_noise", args.track.gps_noise);
+            metadata.param("track_len", args.track.track_len);
+            let tracks = TrackGen::from_seed(args.seed, args.track.clone());
+            if args.mode == Mode::TrackPoints {
+                write_tracks_json(progress.track(tracks.take(count)), &mut writer)?;
+            } else {
+                write_pairs(&args, progress.track(tracks.segments(count)), &mut writer)?;
+            }
+        }
+    }
+    writer.flush()?;
+    progress.report("\n");
+    metadata.count = progress.0.items.get();
+    metadata.bytes = writer.bytes();
+    metadata.checksum = writer.checksum();
+    if let Some(target_bytes) = args.target_bytes {
+        metadata.param("target_bytes", target_bytes);
+    }
+    if to_stdout {
+        return Ok(());
+    }
+
+    let mut sidecar = BufWriter::new(File::create(GenerationMetadata::sidecar_path(path))?);
+    metadata.write(&mut sidecar)?;
+    sidecar.flush()
+}
+
+fn parse_err(err: DeserializationError) -> io::Error {
+    invalid_input(err.to_string())
+}
+
+/// Parses input that is already in memory, for the whole-document parsers and the mmap input
+fn parse_bytes(args: &CalculateArgs, input: &[u8]) -> Result<Vec<CoordPair>, io::Error> {
+    let options = args.parse_options();
+    match (args.format(), args.parser()) {
+        (Format::Json, JsonParser::Tree) => {
+            deserialize_tree_with(input, options).map_err(parse_err)
+        }
+        (Format::Json, parser @ (JsonParser::SinglePass | JsonParser::Parallel)) => {
+            let input = std::str::from_utf8(input).map_err(|e| {
+                invalid_input(format!("input isn't UTF-8 at byte {}", e.valid_up_to()))
+            })?;
+            bench_block!("Deserialize Json");
+            record_bytes(input.len() as u64);
+            if parser == JsonParser::SinglePass {
+                deserialize_single_pass_with(input, options).map_err(parse_err)
+            } else {
+                let threads = match args.threads {
+                    Some(threads) => threads,
+                    None => std::thread::available_parallelism()?.get(),
+                };
+                deserialize_parallel_with(input, threads, options).map_err(parse_err)
+            }
+        }
+        (Format::Json, JsonParser::Structural) => {
+            deserialize_structural_with(input, options).map_err(parse_err)
+        }
+        (format, _) => format
+            .read_pairs_with(&mut &input[..], options)
+            .map_err(parse_err),
+    }
+}
+
+/// Running average of the haversine distances
+struct Average {
+    sum: f64,
+    count: usize,
+    /// Checks each distance as it's computed, when given an answer file
+    answers: Option<AnswerChecker<BufReader<File>>>,
+    /// What the streaming parser read, when asked for
+    stats: Option<ParseStats>,
+    /// The records recovery mode skipped
+    skipped: Option<Skipped>,
+}
+
+impl Average {
+    fn new(args: &CalculateArgs) -> Result<Self, io::Error> {
+        let answers = match &args.answers {
+            Some(path) => Some(AnswerChecker::new(BufReader::new(File::open(path)?))?),
+            None => None,
+        };
+        Ok(Self {
+            sum: 0.0,
+            count: 0,
+            answers,
+            stats: None,
+            skipped: None,
+        })
+    }
+
+    fn add(&mut self, pair: CoordPair) -> Result<(), io::Error> {
+        let distance = naive_haversine(pair);
+        self.sum += distance;
+        self.count += 1;
+        if let Some(answers) = &mut self.answers {
+            answers.add(distance)?;
+        }
+        Ok(())
+    }
+
+    fn of(mut self, pairs: impl IntoIterator<Item = CoordPair>) -> Result<Self, io::Error> {
+        bench_block!("Process Haversine");
+        for pair in pairs {
+            self.add(pair)?;
+        }
+        Ok(self)
+    }
+
+    fn result(&self) -> f64 {
+        self.sum / self.count as f64
+    }
+}
+
+/// `.hvb` is averaged straight from the bytes, without copying the pairs out
+fn average_hvb(args: &CalculateArgs, input: &[u8]) -> Result<Average, io::Error> {
+    let file = HvbFile::new(input).map_err(parse_err)?;
+    if !args.no_verify {
+        bench_block!("Verify Checksum");
+        record_bytes(input.len() as u64);
+        file.verify().map_err(parse_err)?;
+    }
+    Average::new(args)?.of(file.pairs())
+}
+
+/// How many of the skipped records' errors are kept to print
+const MAX_DIAGNOSTICS: usize = 10;
+
+fn read_ndjson_recovering(
+    args: &CalculateArgs,
+    reader: &mut impl BufRead,
+) -> Result<Average, io::Error> {
+    let mut skipped = Skipped::new(MAX_DIAGNOSTICS);
+    let pairs = deserialize_ndjson_recovering(reader, args.parse_options(), &mut skipped)
+        .map_err(parse_err)?;
+    let mut average = Average::new(args)?.of(pairs)?;
+    average.skipped = Some(skipped);
+    Ok(average)
+}
+
+fn read_pairs(
+    args: &CalculateArgs,
+    path: &Path,
+    metadata: Option<&GenerationMetadata>,
+) -> Result<Average, io::Error> {
+    let mut reader = BufReader::new(ChecksumReader::new(File::open(path)?));
+    let res = match (args.format(), args.parser()) {
+        // Averaged as the pairs come in, so memory doesn't grow with the file
+        (Format::Json, JsonParser::Streaming) => {
+            bench_block!("Process Haversine");
+            let mut average = Average::new(args)?;
+            let mut pairs = ArrayIter::new(&mut reader, args.parse_options());
+            if args.max_skipped.is_some() {
+                pairs = pairs.recovering(MAX_DIAGNOSTICS);
+            }
+            for pair in &mut pairs {
+                average.add(pair.map_err(parse_err)?)?;
+            }
+            average.stats = args.stats.then(|| pairs.stats());
+            average.skipped = pairs.into_skipped();
+            average
+        }
+        (format @ (Format::Json | Format::Hvb | Format::HvbColumns), _) => {
+            bench_block!(handle, "Read File");
+            let mut input = Vec::new();
+            reader.read_to_end(&mut input)?;
+            record_bytes(input.len() as u64);
+            drop(handle);
+            match format {
+                Format::Json => Average::new(args)?.of(parse_bytes(args, &input)?)?,
+                _ => average_hvb(args, &input)?,
+            }
+        }
+        (Format::Ndjson, _) if args.max_skipped.is_some() => {
+            read_ndjson_recovering(args, &mut reader)?
+        }
+        (format, _) => Average::new(args)?.of(format
+            .read_pairs_with(&mut reader, args.parse_options())
+            .map_err(parse_err)?)?,
+    };
+    if let Some(metadata) = metadata {
+        io::copy(&mut reader, &mut io::sink())?;
+        let inner = reader.get_ref();
+        metadata
+            .verify(inner.bytes(), inner.checksum())
+            .map_err(invalid_input)?;
+    }
+    Ok(res)
+}
+
+fn map_pairs(
+    args: &CalculateArgs,
+    path: &Path,
+    metadata: Option<&GenerationMetadata>,
+) -> Result<Average, io::Error> {
+    bench_block!(handle, "Map File");
+    let map = Mmap::open(&File::open(path)?)?;
+    for &advice in &args.madvise {
+        if let Err(e) = map.advise(advice) {
+            eprintln!("madvise {} failed: {}", value_name(advice), e);
+        }
+    }
+    drop(handle);
+    let res = match args.format() {
+        Format::Hvb | Format::HvbColumns => average_hvb(args, &map)?,
+        Format::Ndjson if args.max_skipped.is_some() => {
+            read_ndjson_recovering(args, &mut &map[..])?
+        }
+        _ => Average::new(args)?.of(parse_bytes(args, &map)?)?,
+    };
+    if let Some(metadata) = metadata {
+        let mut checksum = Checksum::default();
+        checksum.update(&map);
+        metadata
+            .verify(map.len() as u64, checksum.finish())
+            .map_err(invalid_input)?;
+    }
+    Ok(res)
+}
+
+fn report_skipped(skipped: &Skipped, max_skipped: u64) -> Result<(), io::Error> {
+    println!("Skipped {} records that didn't parse", skipped.count);
+    for err in &skipped.diagnostics {
+        eprintln!("  {err}");
+    }
+    let unlisted = skipped.count - skipped.diagnostics.len() as u64;
+    if unlisted > 0 {
+        eprintln!("  and {unlisted} more");
+    }
+    if skipped.count > max_skipped {
+        return Err(invalid_input(format!(
+            "{} records were skipped, more than the {} allowed",
+            skipped.count, max_skipped
+        )));
+    }
+    Ok(())
+}
+
+fn print_stats(stats: &ParseStats) {
+    println!(
+        "{} records in {} bytes, {} to {} bytes long, {:.1} on average",
+        stats.records,
+        stats.bytes,
+        stats.min_record_len,
+        stats.max_record_len,
+        stats.mean_record_len()
+    );
+    println!(
+        "{} bytes of whitespace, {} skipped keys",
+        stats.whitespace_bytes, stats.ignored_keys
+    );
+}
+
+fn check_answers(path: &Path, average: &mut Average) -> Result<(), io::Error> {
+    let Some(answers) = average.answers.take() else {
+        return Ok(());
+    };
+    let check = answers.finish(average.result())?;
+    println!(
+        "Checked {} distances against {}, largest difference {:e}",
+        check.checked,
+        path.display(),
+        check.largest_difference
+    );
+    if let Some((first, ours, theirs)) = check.first_mismatch {
+        return Err(invalid_input(format!(
+            "{} distances differ from the answers, the first is pair {}: {} but the answer is {}",
+            check.mismatches, first, ours, theirs
+        )));
+    }
+    if !check.average_matches {
+        return Err(invalid_input(format!(
+            "the average differs from the answer {}",
+            check.average
+        )));
+    }
+    Ok(())
+}
+
+fn calculate(path: &Path, mut args: CalculateArgs) -> Result<(), io::Error> {
+    let _benchmark = args.profile.then(Benchmark::init);
+    if args.format.is_none() {
+        let mut head = Vec::new();
+        File::open(path)?.take(4096).read_to_end(&mut head)?;
+        args.format = Format::detect(&head);
+        // The streaming parser only reads a bare array
+        if args.format == Some(Format::Json) && args.parser.is_none() && looks_like_envelope(&head)
+        {
+            args.parser = Some(JsonParser::Tree);
+        }
+    }
+    if args.stats
+        && (args.format() != Format::Json
+            || args.parser() != JsonParser::Streaming
+            || args.input != InputSource::Read)
+    {
+        return Err(invalid_input(
+            "--stats needs the json format with the streaming parser and read input",
+        ));
+    }
+    let recoverable = match args.format() {
+        Format::Json => args.parser() == JsonParser::Streaming && args.input == InputSource::Read,
+        Format::Ndjson => true,
+        _ => false,
+    };
+    if args.max_skipped.is_some() && !recoverable {
+        return Err(invalid_input(
+            "--max-skipped needs the json format with the streaming parser and read input, or ndjson",
+        ));
+    }
+    if args.strict
+        && matches!(
+            args.format(),
+            Format::Csv | Format::Binary | Format::Hvb | Format::HvbColumns
+        )
+    {
+        return Err(invalid_input(
+            "--strict needs one of the json formats, json, ndjson or reference",
+        ));
+    }
+    let metadata = if args.no_verify {
+        None
+    } else {
+        GenerationMetadata::load_for(path).map_err(invalid_input)?
+    };
+    if let Some(metadata) = &metadata {
+        // Catch truncation before the parser trips over it
+        metadata
+            .verify(std::fs::metadata(path)?.len(), metadata.checksum)
+            .map_err(invalid_input)?;
+    }
+    let mut average = match args.input {
+        InputSource::Read => read_pairs(&args, path, metadata.as_ref())?,
+        InputSource::Mmap => map_pairs(&args, path, metadata.as_ref())?,
+    };
+    println!("The avg is: {}", average.result());
+    if let Some(stats) = &average.stats {
+        print_stats(stats);
+    }
+    if let (Some(skipped), Some(max_skipped)) = (&average.skipped, args.max_skipped) {
+        report_skipped(skipped, max_skipped)?;
+    }
+    if let Some(answers) = &args.answers {
+        check_answers(answers, &mut average)?;
+    }
+    Ok(())
 }
 
 fn main() -> Result<(), io::Error> {
     let cli = Cli::parse();
     let path = PathBuf::from(cli.filename);
     match cli.command {
-        Commands::Generate {
-            count,
-            seed,
-            uniform,
-        } => {
-            let rng = StdRng::seed_from_u64(seed);
-            let mut coord_pair_generator = CoordPairGen::new(rng, !uniform, count);
-            let file = File::create(path)?;
-            let mut writer: BufWriter<File> = BufWriter::new(file);
-            serialize(&mut coord_pair_generator, &mut writer)?;
-        }
-        Commands::Calculate {} => {
-            let mut reader = BufReader::new(File::open(path)?);
-            let mut running_sum = 0.0;
-            let res: Vec<CoordPair> = deserialize(&mut reader).unwrap();
-            let len = res.len();
-            for cp in res {
-                let res = naive_haversine(cp);
-                running_sum += res;
-            }
-            let result = running_sum / len as f64;
-            println!("The avg is: {}", result);
-        }
+        Commands::Generate(args) => generate(&path, args)?,
+        Commands::Calculate(args) => calculate(&path, args)?,
     }
     Ok(())
 }
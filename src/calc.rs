@@ -1,6 +1,6 @@
-use crate::CoordPair;
+use crate::{region::wrap_lon, CoordPair};
 
-static EARTH_RADIUS: f64 = 6372.8;
+pub static EARTH_RADIUS: f64 = 6372.8;
 
 pub fn naive_haversine(cp: CoordPair) -> f64 {
     let d_lat = (cp.lat1 - cp.lat0).to_radians();
@@ -12,3 +12,12 @@ pub fn naive_haversine(cp: CoordPair) -> f64 {
     let c = 2.0 * a.sqrt().asin();
     EARTH_RADIUS * c
 }
+
+/// Point reached by travelling `distance` radians of arc from `(lat, lon)` along initial `bearing` (radians from north)
+pub fn destination(lat: f64, lon: f64, bearing: f64, distance: f64) -> (f64, f64) {
+    let lat = lat.to_radians();
+    let lat2 = (lat.sin() * distance.cos() + lat.cos() * distance.sin() * bearing.cos()).asin();
+    let d_lon =
+        (bearing.sin() * distance.sin() * lat.cos()).atan2(distance.cos() - lat.sin() * lat2.sin());
+    (lat2.to_degrees(), wrap_lon(lon + d_lon.to_degrees()))
+}
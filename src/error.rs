@@ -0,0 +1,185 @@
+use std::{error::Error, fmt::Display};
+
+// How far the snippet reaches on each side of the error
+const SNIPPET_RADIUS: usize = 24;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// A byte that can't appear at this point of the input
+    UnexpectedByte(u8),
+    /// A required member of a record is absent
+    MissingMember(String),
+    /// A member the record doesn't have, only an error when parsing strictly
+    UnknownMember(String),
+    /// A member that appears more than once, only an error when parsing strictly
+    DuplicateMember(String),
+    /// A value that should be a number doesn't parse as one
+    BadNumber,
+    /// The input ended in the middle of a value or record
+    TruncatedInput,
+    /// A checksum stored in the input doesn't match its contents
+    BadChecksum,
+    /// Reading the input failed
+    Io(std::io::ErrorKind),
+}
+
+/// Where in the input something happened. Lines and columns are 1-based, columns count bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Position {
+    pub offset: u64,
+    pub line: u64,
+    pub column: u64,
+}
+
+impl Default for Position {
+    fn default() -> Self {
+        Self {
+            offset: 0,
+            line: 1,
+            column: 1,
+        }
+    }
+}
+
+impl Position {
+    /// Moves past `bytes`
+    pub fn advance(&mut self, bytes: &[u8]) {
+        self.offset += bytes.len() as u64;
+        match bytes.iter().rposition(|&b| b == b'\n') {
+            Some(last) => {
+                self.line += bytes.iter().filter(|&&b| b == b'\n').count() as u64;
+                self.column = (bytes.len() - last) as u64;
+            }
+            None => self.column += bytes.len() as u64,
+        }
+    }
+
+    /// This position, taken relative to `base` instead of the start of the input
+    pub fn after(self, base: Position) -> Position {
+        Position {
+            offset: base.offset + self.offset,
+            line: base.line + self.line - 1,
+            column: if self.line == 1 {
+                base.column + self.column - 1
+            } else {
+                self.column
+            },
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct DeserializationError {
+    pub kind: ErrorKind,
+    pub message: String,
+    pub position: Position,
+    /// Index of the record being parsed, if the error happened inside one
+    pub record: Option<usize>,
+    /// The input around `position`, with non-printable bytes escaped
+    pub snippet: String,
+}
+
+impl DeserializationError {
+    pub fn new(kind: ErrorKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+            position: Position::default(),
+            record: None,
+            snippet: String::new(),
+        }
+    }
+
+    /// `member` is required in a `record` and wasn't there
+    pub fn missing_member(record: &str, member: &str) -> Self {
+        Self::new(
+            ErrorKind::MissingMember(member.to_string()),
+            format!("{record} needs a {member} member"),
+        )
+    }
+
+    /// `member` isn't one of the record's own, and strict parsing doesn't skip it
+    pub fn unknown_member(member: &str) -> Self {
+        Self::new(
+            ErrorKind::UnknownMember(member.to_string()),
+            format!("Strict parsing doesn't allow the unknown member {member}"),
+        )
+    }
+
+    /// `member` showed up again, and strict parsing doesn't let the last one win
+    pub fn duplicate_member(member: &str) -> Self {
+        Self::new(
+            ErrorKind::DuplicateMember(member.to_string()),
+            format!("Strict parsing doesn't allow {member} more than once"),
+        )
+    }
+
+    /// Places the error at `offset` of `input`, which is either the whole document or a piece of it to be rebased later
+    pub fn at(mut self, input: &[u8], offset: usize) -> Self {
+        let offset = offset.min(input.len());
+        let mut position = Position::default();
+        position.advance(&input[..offset]);
+        self.position = position;
+        let start = offset.saturating_sub(SNIPPET_RADIUS);
+        let end = (offset + SNIPPET_RADIUS).min(input.len());
+        self.snippet = input[start..end].escape_ascii().to_string();
+        self
+    }
+
+    /// Shifts a position taken relative to a piece of the input to be relative to the whole input
+    pub fn after(mut self, base: Position) -> Self {
+        self.position = self.position.after(base);
+        self
+    }
+
+    /// Records which record the error happened in, unless a more specific one is already known
+    pub fn in_record(mut self, record: usize) -> Self {
+        self.record.get_or_insert(record);
+        self
+    }
+}
+
+impl From<std::io::Error> for DeserializationError {
+    fn from(value: std::io::Error) -> Self {
+        let kind = match value.kind() {
+            std::io::ErrorKind::UnexpectedEof => ErrorKind::TruncatedInput,
+            kind => ErrorKind::Io(kind),
+        };
+        Self::new(kind, value.to_string())
+    }
+}
+
+impl Display for ErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ErrorKind::UnexpectedByte(b) => write!(f, "unexpected byte '{}'", b.escape_ascii()),
+            ErrorKind::MissingMember(name) => write!(f, "member {} missing", name),
+            ErrorKind::UnknownMember(name) => write!(f, "unknown member {}", name),
+            ErrorKind::DuplicateMember(name) => write!(f, "duplicate member {}", name),
+            ErrorKind::BadNumber => write!(f, "bad number"),
+            ErrorKind::TruncatedInput => write!(f, "truncated input"),
+            ErrorKind::BadChecksum => write!(f, "bad checksum"),
+            ErrorKind::Io(kind) => write!(f, "i/o error ({})", kind),
+        }
+    }
+}
+
+impl Display for DeserializationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: {} at line {}, column {} (byte {}",
+            self.kind, self.message, self.position.line, self.position.column, self.position.offset
+        )?;
+        if let Some(record) = self.record {
+            write!(f, ", record {}", record)?;
+        }
+        write!(f, ")")?;
+        if !self.snippet.is_empty() {
+            write!(f, " near `{}`", self.snippet)?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for DeserializationError {}
@@ -0,0 +1,505 @@
+use std::cmp::Ordering;
+
+mod table;
+
+use table::{LARGEST_POWER_OF_FIVE, POWER_OF_FIVE_128, SMALLEST_POWER_OF_FIVE};
+
+// Decimal to f64 straight from bytes, correctly rounded (ties to even) like `str::parse::<f64>`, which it also
+// matches in what it accepts. Three tiers: Clinger's fast path when the mantissa and the power of ten are both
+// exact in an f64, Eisel-Lemire with a 128-bit power of five table for everything else, and an exact big
+// integer comparison for the rare inputs Eisel-Lemire can't decide
+
+const MANTISSA_BITS: i32 = 52;
+const MINIMUM_EXPONENT: i32 = -1023;
+const INFINITE_POWER: i32 = 0x7FF;
+const MIN_EXPONENT_ROUND_TO_EVEN: i64 = -4;
+const MAX_EXPONENT_ROUND_TO_EVEN: i64 = 23;
+const MIN_EXPONENT_FAST_PATH: i64 = -22;
+const MAX_EXPONENT_FAST_PATH: i64 = 22;
+const MAX_EXPONENT_DISGUISED_FAST_PATH: i64 = 37;
+const MAX_MANTISSA_FAST_PATH: u64 = 2 << MANTISSA_BITS;
+// 19 digits always fit a u64
+const MAX_MANTISSA_DIGITS: usize = 19;
+// Digits past this many can only matter through whether any of them is non-zero
+const MAX_SLOW_DIGITS: usize = 800;
+
+const POWERS_OF_TEN: [f64; 23] = [
+    1e0, 1e1, 1e2, 1e3, 1e4, 1e5, 1e6, 1e7, 1e8, 1e9, 1e10, 1e11, 1e12, 1e13, 1e14, 1e15, 1e16,
+    1e17, 1e18, 1e19, 1e20, 1e21, 1e22,
+];
+
+/// Whether `bytes` is a number the way JSON spells them: an optional `-`, an integer part without leading
+/// zeros, then an optional fraction and exponent. `parse_f64` also takes `+1`, `.5`, `1.`, `inf` and `NaN`
+pub fn is_json_number(bytes: &[u8]) -> bool {
+    let digits = |from: usize| {
+        from + bytes[from..]
+            .iter()
+            .take_while(|b| b.is_ascii_digit())
+            .count()
+    };
+    let mut pos = usize::from(bytes.first() == Some(&b'-'));
+    pos = match bytes.get(pos) {
+        Some(b'0') => pos + 1,
+        Some(b'1'..=b'9') => digits(pos),
+        _ => return false,
+    };
+    if bytes.get(pos) == Some(&b'.') {
+        let end = digits(pos + 1);
+        if end == pos + 1 {
+            return false;
+        }
+        pos = end;
+    }
+    if let Some(b'e' | b'E') = bytes.get(pos) {
+        pos += 1;
+        if let Some(b'+' | b'-') = bytes.get(pos) {
+            pos += 1;
+        }
+        let end = digits(pos);
+        if end == pos {
+            return false;
+        }
+        pos = end;
+    }
+    pos == bytes.len()
+}
+
+/// Parses the whole of `bytes` as a decimal float. `None` if it isn't one
+pub fn parse_f64(bytes: &[u8]) -> Option<f64> {
+    let (negative, rest) = match bytes.split_first() {
+        Some((b'-', rest)) => (true, rest),
+        Some((b'+', rest)) => (false, rest),
+        _ => (false, bytes),
+    };
+    let val = match parse_decimal(rest) {
+        Some(decimal) => decimal.to_f64(),
+        None => parse_special(rest)?,
+    };
+    Some(if negative { -val } else { val })
+}
+
+fn parse_special(bytes: &[u8]) -> Option<f64> {
+    if bytes.eq_ignore_ascii_case(b"inf") || bytes.eq_ignore_ascii_case(b"infinity") {
+        Some(f64::INFINITY)
+    } else if bytes.eq_ignore_ascii_case(b"nan") {
+        Some(f64::NAN)
+    } else {
+        None
+    }
+}
+
+/// `int.frac * 10^exp`, with the first 19 significant digits folded into `mantissa * 10^exponent`
+struct Decimal<'a> {
+    int: &'a [u8],
+    frac: &'a [u8],
+    exp: i64,
+    mantissa: u64,
+    exponent: i64,
+    /// More significant digits than fit in `mantissa`
+    many_digits: bool,
+}
+
+fn take_digits(bytes: &[u8]) -> (&[u8], &[u8]) {
+    let len = bytes.iter().take_while(|b| b.is_ascii_digit()).count();
+    bytes.split_at(len)
+}
+
+/// The 8 bytes as a little-endian word if they're all ASCII digits
+fn eight_digits(bytes: &[u8]) -> Option<u64> {
+    let word = u64::from_le_bytes(bytes.get(..8)?.try_into().ok()?);
+    // Every byte in 0x30..=0x39: adding 0x46 carries into the top bit exactly for bytes above 0x39
+    let invalid = (word.wrapping_add(0x4646_4646_4646_4646)
+        | word.wrapping_sub(0x3030_3030_3030_3030))
+        & 0x8080_8080_8080_8080;
+    (invalid == 0).then_some(word)
+}
+
+/// SWAR: combines 8 ASCII digits pairwise, then into fours, then into the full number.
+/// The high halves of the products are garbage that the shifts and masks throw away
+fn parse_eight_digits(word: u64) -> u64 {
+    let word = word - 0x3030_3030_3030_3030;
+    let word = word.wrapping_mul(10).wrapping_add(word >> 8);
+    let high = (word & 0x0000_00FF_0000_00FF).wrapping_mul(100 + (1_000_000 << 32));
+    let low = ((word >> 16) & 0x0000_00FF_0000_00FF).wrapping_mul(1 + (10_000 << 32));
+    (high.wrapping_add(low) >> 32) & 0xFFFF_FFFF
+}
+
+fn parse_decimal(bytes: &[u8]) -> Option<Decimal<'_>> {
+    // Accumulate as we go, the mantissa is only kept as is when there turn out to be 19 digits or fewer
+    let mut mantissa = 0u64;
+    let mut i = 0;
+    while let Some(&d) = bytes.get(i).filter(|d| d.is_ascii_digit()) {
+        mantissa = mantissa.wrapping_mul(10).wrapping_add((d - b'0') as u64);
+        i += 1;
+    }
+    let int = &bytes[..i];
+    let mut frac = &bytes[i..i];
+    if bytes.get(i) == Some(&b'.') {
+        i += 1;
+        let start = i;
+        while let Some(word) = eight_digits(&bytes[i..]) {
+            mantissa = mantissa
+                .wrapping_mul(100_000_000)
+                .wrapping_add(parse_eight_digits(word));
+            i += 8;
+        }
+        while let Some(&d) = bytes.get(i).filter(|d| d.is_ascii_digit()) {
+            mantissa = mantissa.wrapping_mul(10).wrapping_add((d - b'0') as u64);
+            i += 1;
+        }
+        frac = &bytes[start..i];
+    }
+    if int.is_empty() && frac.is_empty() {
+        return None;
+    }
+    let exp = match bytes[i..].split_first() {
+        None => 0,
+        Some((b'e' | b'E', rest)) => {
+            let (negative, rest) = match rest.split_first() {
+                Some((b'-', rest)) => (true, rest),
+                Some((b'+', rest)) => (false, rest),
+                _ => (false, rest),
+            };
+            let (digits, rest) = take_digits(rest);
+            if digits.is_empty() || !rest.is_empty() {
+                return None;
+            }
+            // Saturate, anything this large is zero or infinity either way
+            let exp = digits
+                .iter()
+                .fold(0i64, |acc, &d| (acc * 10 + (d - b'0') as i64).min(0x10000));
+            if negative {
+                -exp
+            } else {
+                exp
+            }
+        }
+        Some(_) => return None,
+    };
+    let mut decimal = Decimal {
+        int,
+        frac,
+        exp,
+        mantissa,
+        exponent: exp - frac.len() as i64,
+        many_digits: false,
+    };
+    if int.len() + frac.len() > MAX_MANTISSA_DIGITS {
+        decimal.truncate();
+    }
+    Some(decimal)
+}
+
+impl Decimal<'_> {
+    /// Redoes the mantissa from the first 19 significant digits when there were more than that
+    fn truncate(&mut self) {
+        let mut mantissa = 0u64;
+        let mut digits = 0;
+        let mut frac_digits = 0i64;
+        // Index of the first significant digit that didn't fit in the mantissa
+        let mut cut = None;
+        for (i, &d) in self.int.iter().chain(self.frac).enumerate() {
+            if digits == MAX_MANTISSA_DIGITS {
+                cut = Some(i);
+                break;
+            }
+            if i >= self.int.len() {
+                frac_digits += 1;
+            }
+            if digits != 0 || d != b'0' {
+                mantissa = mantissa * 10 + (d - b'0') as u64;
+                digits += 1;
+            }
+        }
+        let dropped_int_digits = cut.map_or(0, |cut| self.int.len().saturating_sub(cut) as i64);
+        self.mantissa = mantissa;
+        self.exponent = self.exp + dropped_int_digits - frac_digits;
+        // Zeros past the 19th digit don't make the mantissa inexact
+        self.many_digits = cut.is_some_and(|cut| {
+            self.int
+                .iter()
+                .chain(self.frac)
+                .skip(cut)
+                .any(|&d| d != b'0')
+        });
+    }
+
+    fn to_f64(&self) -> f64 {
+        if let Some(val) = self.fast_path() {
+            return val;
+        }
+        if self.mantissa == 0 {
+            return 0.0;
+        }
+        let (bits, exact) = lemire(self.exponent, self.mantissa);
+        if exact && !self.many_digits {
+            return f64::from_bits(bits);
+        }
+        // With digits cut off the true value is somewhere in [mantissa, mantissa + 1) * 10^exponent,
+        // if both ends round to the same float that's the answer
+        if exact && lemire(self.exponent, self.mantissa + 1) == (bits, true) {
+            return f64::from_bits(bits);
+        }
+        self.slow_path(bits)
+    }
+
+    /// Clinger: with both the mantissa and the power of ten exact, a single IEEE operation rounds correctly
+    fn fast_path(&self) -> Option<f64> {
+        if self.many_digits
+            || self.mantissa > MAX_MANTISSA_FAST_PATH
+            || !(MIN_EXPONENT_FAST_PATH..=MAX_EXPONENT_DISGUISED_FAST_PATH).contains(&self.exponent)
+        {
+            return None;
+        }
+        if self.exponent < 0 {
+            return Some(self.mantissa as f64 / POWERS_OF_TEN[-self.exponent as usize]);
+        }
+        if self.exponent <= MAX_EXPONENT_FAST_PATH {
+            return Some(self.mantissa as f64 * POWERS_OF_TEN[self.exponent as usize]);
+        }
+        // 1.5e30 is 15e29: move the excess exponent into the mantissa while it stays exact
+        let shift = self.exponent - MAX_EXPONENT_FAST_PATH;
+        let mantissa = self.mantissa.checked_mul(10u64.pow(shift as u32))?;
+        if mantissa > MAX_MANTISSA_FAST_PATH {
+            return None;
+        }
+        Some(mantissa as f64 * POWERS_OF_TEN[MAX_EXPONENT_FAST_PATH as usize])
+    }
+
+    /// Walks from `guess` to the float whose rounding interval holds the exact decimal value
+    fn slow_path(&self, guess: u64) -> f64 {
+        let (digits, exponent) = self.big_digits();
+        let mut bits = guess.min(f64::MAX.to_bits());
+        loop {
+            if bits == f64::INFINITY.to_bits() {
+                return f64::INFINITY;
+            }
+            match compare_midpoint(&digits, exponent, bits) {
+                Ordering::Greater => {
+                    bits += 1;
+                    continue;
+                }
+                // Ties go to the even mantissa, and past f64::MAX that's infinity
+                Ordering::Equal => return f64::from_bits(bits + (bits & 1)),
+                Ordering::Less => {}
+            }
+            if bits == 0 {
+                return 0.0;
+            }
+            match compare_midpoint(&digits, exponent, bits - 1) {
+                Ordering::Less => bits -= 1,
+                Ordering::Equal => return f64::from_bits(bits - (bits & 1)),
+                Ordering::Greater => return f64::from_bits(bits),
+            }
+        }
+    }
+
+    /// The significant digits as a big integer and the power of ten it's scaled by
+    fn big_digits(&self) -> (BigUint, i64) {
+        let all = || self.int.iter().chain(self.frac);
+        let leading_zeros = all().take_while(|&&d| d == b'0').count();
+        let significant = self.int.len() + self.frac.len() - leading_zeros;
+        let taken = significant.min(MAX_SLOW_DIGITS);
+        let mut digits = BigUint::default();
+        for &d in all().skip(leading_zeros).take(taken) {
+            digits.mul_small(10);
+            digits.add_small((d - b'0') as u32);
+        }
+        let mut exponent = self.exp - self.frac.len() as i64 + (significant - taken) as i64;
+        // Past this many digits only whether the rest is zero can change the rounding
+        if all().skip(leading_zeros + taken).any(|&d| d != b'0') {
+            digits.mul_small(10);
+            digits.add_small(1);
+            exponent -= 1;
+        }
+        (digits, exponent)
+    }
+}
+
+/// Eisel-Lemire. Returns the bits of `w * 10^q` and whether the result is certain, otherwise it's within an ulp
+fn lemire(q: i64, mut w: u64) -> (u64, bool) {
+    if q < SMALLEST_POWER_OF_FIVE {
+        return (0, true);
+    }
+    if q > LARGEST_POWER_OF_FIVE {
+        return (f64::INFINITY.to_bits(), true);
+    }
+    let lz = w.leading_zeros();
+    w <<= lz;
+    let (lo, hi) = product_approx(q, w, MANTISSA_BITS as u32 + 3);
+    // The truncated table entry may have cut off a carry into the bits we keep
+    let exact = lo != u64::MAX || (-27..=55).contains(&q);
+    let upper_bit = (hi >> 63) as i32;
+    let mut mantissa = hi >> (upper_bit + 64 - MANTISSA_BITS - 3);
+    let mut power2 = power(q as i32) + upper_bit - lz as i32 - MINIMUM_EXPONENT;
+    if power2 <= 0 {
+        // Subnormal
+        if -power2 + 1 >= 64 {
+            return (0, exact);
+        }
+        mantissa >>= -power2 + 1;
+        mantissa += mantissa & 1;
+        mantissa >>= 1;
+        power2 = (mantissa >= (1 << MANTISSA_BITS)) as i32;
+        return (
+            ((power2 as u64) << MANTISSA_BITS) | (mantissa & ((1 << MANTISSA_BITS) - 1)),
+            exact,
+        );
+    }
+    // Exactly halfway between two floats, which only happens for small powers: round to even, not up
+    if lo <= 1
+        && (MIN_EXPONENT_ROUND_TO_EVEN..=MAX_EXPONENT_ROUND_TO_EVEN).contains(&q)
+        && mantissa & 3 == 1
+        && (mantissa << (upper_bit + 64 - MANTISSA_BITS - 3)) == hi
+    {
+        mantissa &= !1;
+    }
+    mantissa += mantissa & 1;
+    mantissa >>= 1;
+    if mantissa >= (2 << MANTISSA_BITS) {
+        mantissa = 1 << MANTISSA_BITS;
+        power2 += 1;
+    }
+    mantissa &= !(1 << MANTISSA_BITS);
+    if power2 >= INFINITE_POWER {
+        return (f64::INFINITY.to_bits(), exact);
+    }
+    (((power2 as u64) << MANTISSA_BITS) | mantissa, exact)
+}
+
+/// floor(log2(10^q)) + 63
+fn power(q: i32) -> i32 {
+    (q.wrapping_mul(152_170 + 65536) >> 16) + 63
+}
+
+/// Top 128 bits of `w * 5^q`, only as precise as the `precision` top bits need
+fn product_approx(q: i64, w: u64, precision: u32) -> (u64, u64) {
+    let mask = u64::MAX >> precision;
+    let (hi5, lo5) = POWER_OF_FIVE_128[(q - SMALLEST_POWER_OF_FIVE) as usize];
+    let first = w as u128 * hi5 as u128;
+    let (mut lo, mut hi) = (first as u64, (first >> 64) as u64);
+    if hi & mask == mask {
+        let second_hi = ((w as u128 * lo5 as u128) >> 64) as u64;
+        lo = lo.wrapping_add(second_hi);
+        if second_hi > lo {
+            hi += 1;
+        }
+    }
+    (lo, hi)
+}
+
+/// Compares `digits * 10^exponent` against the point halfway between the positive float `bits` and the next one up
+fn compare_midpoint(digits: &BigUint, exponent: i64, bits: u64) -> Ordering {
+    let biased = (bits >> MANTISSA_BITS) as i64;
+    let fraction = bits & ((1 << MANTISSA_BITS) - 1);
+    let (mantissa, power2) = if biased == 0 {
+        (fraction, -1074)
+    } else {
+        (fraction | (1 << MANTISSA_BITS), biased - 1075)
+    };
+    // Halfway is (2 * mantissa + 1) * 2^(power2 - 1)
+    let mut lhs = digits.clone();
+    let mut rhs = BigUint::from_u64(2 * mantissa + 1);
+    let rhs_power2 = power2 - 1;
+    // digits * 2^e * 5^e against rhs * 2^p, with the power of five moved to whichever side keeps it integral
+    if exponent >= 0 {
+        lhs.mul_pow5(exponent as u32);
+    } else {
+        rhs.mul_pow5(-exponent as u32);
+    }
+    if exponent > rhs_power2 {
+        lhs.shl((exponent - rhs_power2) as u32);
+    } else {
+        rhs.shl((rhs_power2 - exponent) as u32);
+    }
+    lhs.cmp(&rhs)
+}
+
+/// Just enough of an arbitrary precision integer for `compare_midpoint`. Little-endian 32-bit limbs
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct BigUint(Vec<u32>);
+
+impl BigUint {
+    fn from_u64(val: u64) -> Self {
+        let mut out = Self(vec![val as u32, (val >> 32) as u32]);
+        out.trim();
+        out
+    }
+
+    fn trim(&mut self) {
+        while self.0.last() == Some(&0) {
+            self.0.pop();
+        }
+    }
+
+    fn mul_small(&mut self, factor: u32) {
+        let mut carry = 0u64;
+        for limb in &mut self.0 {
+            let product = *limb as u64 * factor as u64 + carry;
+            *limb = product as u32;
+            carry = product >> 32;
+        }
+        if carry != 0 {
+            self.0.push(carry as u32);
+        }
+    }
+
+    fn add_small(&mut self, val: u32) {
+        let mut carry = val as u64;
+        for limb in &mut self.0 {
+            if carry == 0 {
+                return;
+            }
+            let sum = *limb as u64 + carry;
+            *limb = sum as u32;
+            carry = sum >> 32;
+        }
+        if carry != 0 {
+            self.0.push(carry as u32);
+        }
+    }
+
+    fn mul_pow5(&mut self, mut exp: u32) {
+        // 5^13 is the largest power of five below 2^32
+        while exp >= 13 {
+            self.mul_small(1_220_703_125);
+            exp -= 13;
+        }
+        self.mul_small(5u32.pow(exp));
+    }
+
+    fn shl(&mut self, bits: u32) {
+        if self.0.is_empty() {
+            return;
+        }
+        let limbs = (bits / 32) as usize;
+        let bits = bits % 32;
+        if bits != 0 {
+            let mut carry = 0;
+            for limb in &mut self.0 {
+                let shifted = ((*limb as u64) << bits) | carry;
+                *limb = shifted as u32;
+                carry = shifted >> 32;
+            }
+            if carry != 0 {
+                self.0.push(carry as u32);
+            }
+        }
+        self.0.splice(0..0, std::iter::repeat_n(0, limbs));
+    }
+}
+
+impl PartialOrd for BigUint {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for BigUint {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0
+            .len()
+            .cmp(&other.0.len())
+            .then_with(|| self.0.iter().rev().cmp(other.0.iter().rev()))
+    }
+}
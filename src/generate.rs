@@ -1,20 +1,122 @@
-use rand::Rng;
+use std::{fmt, str::FromStr};
 
-use crate::CoordPair;
+use clap::ValueEnum;
+use rand::{RngCore, SeedableRng};
+
+use crate::{
+    calc::destination,
+    region::{wrap_lon, BoundingBox, Region},
+    rng::{gen_normal, gen_range, gen_unit, Xoshiro256StarStar},
+    CoordPair,
+};
+
+/// How points are spread around a cluster centre
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ClusterShape {
+    /// Random lat/lon box, clamped at the poles
+    Box,
+    /// Gaussian in the tangent plane at the centre, with sigma = 1/sqrt(concentration) radians
+    Gaussian,
+    /// von Mises-Fisher on the sphere with kappa = concentration
+    #[value(alias = "vmf")]
+    VonMisesFisher,
+}
+
+/// How tightly gaussian and von Mises-Fisher clusters hold together, higher is tighter. Always positive and finite
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Concentration(f64);
+
+impl Concentration {
+    pub fn new(val: f64) -> Option<Self> {
+        // Infinity would make a gaussian's sigma 0 and put every point on the centre
+        (val.is_finite() && val > 0.0).then_some(Self(val))
+    }
+
+    pub fn get(self) -> f64 {
+        self.0
+    }
+}
+
+impl FromStr for Concentration {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let val: f64 = s
+            .trim()
+            .parse()
+            .map_err(|_| format!("invalid concentration '{s}'"))?;
+        Self::new(val)
+            .ok_or_else(|| format!("concentration has to be positive and finite, found '{s}'"))
+    }
+}
+
+impl fmt::Display for Concentration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// The cluster shapes that spread points around their centre rather than drawing them from a box
+#[derive(Debug, Clone, Copy)]
+enum Spread {
+    Gaussian { sigma: f64 },
+    VonMisesFisher { kappa: f64 },
+}
+
+impl Spread {
+    fn new(shape: ClusterShape, concentration: Concentration) -> Option<Self> {
+        match shape {
+            ClusterShape::Box => None,
+            ClusterShape::Gaussian => Some(Spread::Gaussian {
+                sigma: 1.0 / concentration.get().sqrt(),
+            }),
+            ClusterShape::VonMisesFisher => Some(Spread::VonMisesFisher {
+                kappa: concentration.get(),
+            }),
+        }
+    }
+}
+
+// Both spread the same way for large concentrations, vMF just stays well defined when the spread approaches the whole sphere
+fn sample_around(spread: Spread, (lat, lon): (f64, f64), rng: &mut impl RngCore) -> (f64, f64) {
+    let (bearing, distance) = match spread {
+        Spread::Gaussian { sigma } => {
+            let north = gen_normal(rng) * sigma;
+            let east = gen_normal(rng) * sigma;
+            (east.atan2(north), north.hypot(east))
+        }
+        Spread::VonMisesFisher { kappa } => {
+            // Inverse CDF of the cosine of the angle to the mean direction
+            let u = 1.0 - gen_unit(rng);
+            let w = 1.0 + (u + (1.0 - u) * (-2.0 * kappa).exp()).ln() / kappa;
+            let bearing = gen_range(rng, 0.0, std::f64::consts::TAU);
+            (bearing, w.clamp(-1.0, 1.0).acos())
+        }
+    };
+    destination(lat, lon, bearing, distance)
+}
 
 fn gen_rand_lat_lon(
     min_lat: f64,
     max_lat: f64,
     min_lon: f64,
     max_lon: f64,
-    rng: &mut impl Rng,
+    rng: &mut impl RngCore,
 ) -> (f64, f64) {
-    let lat = rng.gen_range(min_lat..max_lat);
-    let lon = rng.gen_range(min_lon..max_lon);
+    let lat = gen_range(rng, min_lat, max_lat);
+    let lon = gen_range(rng, min_lon, max_lon);
     (lat, lon)
 }
 
-pub struct CoordPairGen<T> {
+/// A cluster as it was generated. Box clusters report the box points were drawn from,
+/// gaussian and von Mises-Fisher clusters the lat/lon extent of the points actually produced
+#[derive(Debug, Clone, Copy)]
+pub struct Cluster {
+    pub center: (f64, f64),
+    pub bounds: BoundingBox,
+}
+
+pub struct CoordPairGen<T = Xoshiro256StarStar> {
     cur_item: usize,
     item_count: usize,
     min_lat: f64,
@@ -22,10 +124,24 @@ pub struct CoordPairGen<T> {
     min_lon: f64,
     max_lon: f64,
     should_cluster: bool,
+    region: Option<Region>,
+    /// `None` for box clusters
+    spread: Option<Spread>,
+    clusters: Vec<Cluster>,
     rng: T,
 }
 
-impl<T: Rng> CoordPairGen<T> {
+impl CoordPairGen {
+    pub fn from_seed(seed: u64, should_cluster: bool, item_count: usize) -> Self {
+        Self::new(
+            Xoshiro256StarStar::seed_from_u64(seed),
+            should_cluster,
+            item_count,
+        )
+    }
+}
+
+impl<T: RngCore> CoordPairGen<T> {
     pub fn new(rng: T, should_cluster: bool, item_count: usize) -> Self {
         Self {
             cur_item: 0,
@@ -36,49 +152,118 @@ impl<T: Rng> CoordPairGen<T> {
             min_lon: -180.0,
             max_lon: 180.0,
             should_cluster,
+            region: None,
+            spread: None,
+            clusters: Vec::new(),
         }
     }
+
+    pub fn clusters(&self) -> &[Cluster] {
+        &self.clusters
+    }
+
+    /// `concentration` is ignored for box clusters
+    pub fn with_cluster_shape(mut self, shape: ClusterShape, concentration: Concentration) -> Self {
+        self.spread = Spread::new(shape, concentration);
+        self
+    }
+
+    /// Restricts all points to `region`, sampled uniformly by area. Clusters are placed inside the region too
+    pub fn with_region(mut self, region: Region) -> Self {
+        let bounds = region.bounds();
+        self.min_lat = bounds.min_lat;
+        self.max_lat = bounds.max_lat;
+        self.min_lon = bounds.min_lon;
+        self.max_lon = bounds.max_lon;
+        self.region = Some(region);
+        self
+    }
+
     fn start_new_cluster(&mut self) {
-        let lat_center: f64 = self.rng.gen_range(-90.0..90.0);
-        let lon_center: f64 = self.rng.gen_range(-180.0..180.0);
-        let lat_radius: f64 = self.rng.gen_range(0.0..90.0);
-        let lon_radius: f64 = self.rng.gen_range(0.0..180.0);
-        self.min_lat = (lat_center - lat_radius).clamp(-90.0, 90.0);
-        self.max_lat = (lat_center + lat_radius).clamp(-90.0, 90.0);
-        self.min_lon = (lon_center - lon_radius).clamp(-180.0, 180.0);
-        self.max_lon = (lon_center + lon_radius).clamp(-180.0, 180.0);
+        let center = if self.spread.is_some() {
+            let (lat, lon) = match &self.region {
+                Some(region) => region.sample(&mut self.rng),
+                None => BoundingBox::GLOBE.sample(&mut self.rng),
+            };
+            (self.min_lat, self.max_lat, self.min_lon, self.max_lon) = (lat, lat, lon, lon);
+            (lat, lon)
+        } else if let Some(region) = &self.region {
+            let bounds = region.bounds();
+            let (lat_center, lon_center) = region.sample(&mut self.rng);
+            let lat_radius = gen_range(&mut self.rng, 0.0, (bounds.max_lat - bounds.min_lat) / 2.0);
+            let lon_radius = gen_range(&mut self.rng, 0.0, bounds.lon_span() / 2.0);
+            self.min_lat = (lat_center - lat_radius).max(bounds.min_lat);
+            self.max_lat = (lat_center + lat_radius).min(bounds.max_lat);
+            self.min_lon = wrap_lon(lon_center - lon_radius);
+            self.max_lon = wrap_lon(lon_center + lon_radius);
+            (lat_center, lon_center)
+        } else {
+            let lat_center = gen_range(&mut self.rng, -90.0, 90.0);
+            let lon_center = gen_range(&mut self.rng, -180.0, 180.0);
+            let lat_radius = gen_range(&mut self.rng, 0.0, 90.0);
+            let lon_radius = gen_range(&mut self.rng, 0.0, 180.0);
+            self.min_lat = (lat_center - lat_radius).clamp(-90.0, 90.0);
+            self.max_lat = (lat_center + lat_radius).clamp(-90.0, 90.0);
+            self.min_lon = (lon_center - lon_radius).clamp(-180.0, 180.0);
+            self.max_lon = (lon_center + lon_radius).clamp(-180.0, 180.0);
+            (lat_center, lon_center)
+        };
+        self.clusters.push(Cluster {
+            center,
+            bounds: self.cluster_bounds(),
+        });
+    }
+
+    fn cluster_bounds(&self) -> BoundingBox {
+        BoundingBox {
+            min_lat: self.min_lat,
+            max_lat: self.max_lat,
+            min_lon: self.min_lon,
+            max_lon: self.max_lon,
+        }
     }
 }
 
-impl<T: Rng> Iterator for CoordPairGen<T> {
+impl<T: RngCore> CoordPairGen<T> {
+    fn gen_point(&mut self) -> (f64, f64) {
+        if let (Some(spread), Some(cluster)) = (self.spread, self.clusters.last_mut()) {
+            let propose = |rng: &mut T| sample_around(spread, cluster.center, rng);
+            let (lat, lon) = match &self.region {
+                Some(region) => region.sample_by(&mut self.rng, propose),
+                None => propose(&mut self.rng),
+            };
+            cluster.bounds.grow_to(lat, lon, cluster.center.1);
+            return (lat, lon);
+        }
+        match &self.region {
+            None => gen_rand_lat_lon(
+                self.min_lat,
+                self.max_lat,
+                self.min_lon,
+                self.max_lon,
+                &mut self.rng,
+            ),
+            Some(region) => region.sample_within(&self.cluster_bounds(), &mut self.rng),
+        }
+    }
+}
+
+impl<T: RngCore> Iterator for CoordPairGen<T> {
     type Item = CoordPair;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.item_count < self.cur_item {
+        if self.cur_item >= self.item_count {
             return None;
         }
-        if self.should_cluster && self.cur_item % 1000 == 0 {
+        if self.should_cluster && self.cur_item.is_multiple_of(1000) {
             self.start_new_cluster();
         }
         self.cur_item += 1;
-        Some(
-            (
-                gen_rand_lat_lon(
-                    self.min_lat,
-                    self.max_lat,
-                    self.min_lon,
-                    self.max_lon,
-                    &mut self.rng,
-                ),
-                gen_rand_lat_lon(
-                    self.min_lat,
-                    self.max_lat,
-                    self.min_lon,
-                    self.max_lon,
-                    &mut self.rng,
-                ),
-            )
-                .into(),
-        )
+        Some((self.gen_point(), self.gen_point()).into())
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.item_count.saturating_sub(self.cur_item);
+        (remaining, Some(remaining))
     }
 }
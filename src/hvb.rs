@@ -0,0 +1,314 @@
+use std::io::{self, Write};
+
+use crate::{
+    metadata::Checksum,
+    parser::{unexpected_at, DeserializationError, ErrorKind},
+    CoordPair,
+};
+
+// `.hvb`, a self-describing binary pair file, read in place so there's no text parsing at all.
+//
+//   0..4    magic `HVB\0`
+//   4..6    version, u16
+//   6..8    endianness marker, 0x0102 as a u16 in the file's byte order
+//   8       layout, 0 for a row of four coordinates per pair, 1 for a column per coordinate
+//   9..16   reserved, zero
+//   16..24  pair count, u64
+//   24..    count * 4 f64 coordinates, lat0, lon0, lat1, lon1
+//   last 8  FNV-1a checksum of everything before it, u64
+//
+// All numbers are in the byte order the marker says. The coordinates start 8 byte aligned, so a mapped
+// file in the machine's byte order can be used as a `&[f64]` directly
+
+pub const HVB_MAGIC: [u8; 4] = *b"HVB\0";
+pub const HVB_VERSION: u16 = 1;
+const ENDIAN_MARKER: u16 = 0x0102;
+pub const HVB_HEADER_LEN: usize = 24;
+const CHECKSUM_LEN: usize = 8;
+const PAIR_LEN: usize = 32;
+
+/// How the coordinates of the pairs are arranged
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Layout {
+    /// lat0, lon0, lat1 and lon1 of one pair, then the next pair
+    Rows,
+    /// Every lat0, then every lon0, every lat1 and every lon1
+    Columns,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Endian {
+    Little,
+    Big,
+}
+
+impl Endian {
+    #[cfg(target_endian = "little")]
+    pub const NATIVE: Endian = Endian::Little;
+    #[cfg(target_endian = "big")]
+    pub const NATIVE: Endian = Endian::Big;
+
+    fn u16_bytes(self, val: u16) -> [u8; 2] {
+        match self {
+            Endian::Little => val.to_le_bytes(),
+            Endian::Big => val.to_be_bytes(),
+        }
+    }
+
+    fn u64_bytes(self, val: u64) -> [u8; 8] {
+        match self {
+            Endian::Little => val.to_le_bytes(),
+            Endian::Big => val.to_be_bytes(),
+        }
+    }
+
+    fn read_u16(self, bytes: &[u8]) -> u16 {
+        let bytes = bytes[..2].try_into().unwrap();
+        match self {
+            Endian::Little => u16::from_le_bytes(bytes),
+            Endian::Big => u16::from_be_bytes(bytes),
+        }
+    }
+
+    fn read_u64(self, bytes: &[u8]) -> u64 {
+        let bytes = bytes[..8].try_into().unwrap();
+        match self {
+            Endian::Little => u64::from_le_bytes(bytes),
+            Endian::Big => u64::from_be_bytes(bytes),
+        }
+    }
+
+    #[inline(always)]
+    fn read_f64(self, bytes: &[u8]) -> f64 {
+        f64::from_bits(self.read_u64(bytes))
+    }
+}
+
+/// The fewest pairs an `.hvb` file needs to be at least `len` bytes long
+pub fn hvb_count_for_len(len: u64) -> usize {
+    let body = len.saturating_sub((HVB_HEADER_LEN + CHECKSUM_LEN) as u64);
+    usize::try_from(body.div_ceil(PAIR_LEN as u64)).unwrap_or(usize::MAX)
+}
+
+/// Writes `pairs` as an `.hvb` file. The count goes in the header, so unless `pairs` knows exactly how many
+/// it has, they're gathered first. Columns are always gathered, every pair is needed before the first lon0
+pub fn write_hvb(
+    pairs: impl Iterator<Item = CoordPair>,
+    writer: &mut impl Write,
+    layout: Layout,
+    endian: Endian,
+) -> Result<(), io::Error> {
+    match pairs.size_hint() {
+        (lower, Some(upper)) if lower == upper => {
+            write_hvb_counted(pairs, lower, writer, layout, endian)
+        }
+        _ => {
+            let pairs: Vec<CoordPair> = pairs.collect();
+            let count = pairs.len();
+            write_hvb_counted(pairs.into_iter(), count, writer, layout, endian)
+        }
+    }
+}
+
+fn write_hvb_counted(
+    pairs: impl Iterator<Item = CoordPair>,
+    count: usize,
+    writer: &mut impl Write,
+    layout: Layout,
+    endian: Endian,
+) -> Result<(), io::Error> {
+    let mut checksum = Checksum::default();
+    let mut write = |bytes: &[u8]| {
+        checksum.update(bytes);
+        writer.write_all(bytes)
+    };
+
+    write(&HVB_MAGIC)?;
+    write(&endian.u16_bytes(HVB_VERSION))?;
+    write(&endian.u16_bytes(ENDIAN_MARKER))?;
+    write(&[match layout {
+        Layout::Rows => 0,
+        Layout::Columns => 1,
+    }])?;
+    write(&[0; 7])?;
+    write(&endian.u64_bytes(count as u64))?;
+
+    let coordinate = |pair: &CoordPair, i: usize| [pair.lat0, pair.lon0, pair.lat1, pair.lon1][i];
+    let mut written = 0;
+    match layout {
+        Layout::Rows => {
+            for pair in pairs {
+                for i in 0..4 {
+                    write(&endian.u64_bytes(coordinate(&pair, i).to_bits()))?;
+                }
+                written += 1;
+            }
+        }
+        Layout::Columns => {
+            let pairs: Vec<CoordPair> = pairs.collect();
+            for i in 0..4 {
+                for pair in &pairs {
+                    write(&endian.u64_bytes(coordinate(pair, i).to_bits()))?;
+                }
+            }
+            written = pairs.len();
+        }
+    }
+    if written != count {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("the header says {count} pairs but {written} were written"),
+        ));
+    }
+    let sum = checksum.finish();
+    writer.write_all(&endian.u64_bytes(sum))
+}
+
+/// An `.hvb` file borrowed in place. Creating one checks the header and the length, `verify` the checksum
+#[derive(Debug, Clone, Copy)]
+pub struct HvbFile<'a> {
+    pub layout: Layout,
+    pub endian: Endian,
+    count: usize,
+    input: &'a [u8],
+    body: &'a [u8],
+}
+
+impl<'a> HvbFile<'a> {
+    pub fn new(input: &'a [u8]) -> Result<Self, DeserializationError> {
+        let header = &input[..input.len().min(HVB_HEADER_LEN)];
+        if let Some(offset) = header.iter().zip(HVB_MAGIC).position(|(&a, b)| a != b) {
+            return Err(unexpected_at(input, offset, "Expected the .hvb magic"));
+        }
+        if header.len() < HVB_HEADER_LEN {
+            return Err(DeserializationError::new(
+                ErrorKind::TruncatedInput,
+                "Expected the rest of the .hvb header",
+            )
+            .at(input, input.len()));
+        }
+        let endian = match Endian::Little.read_u16(&header[6..]) {
+            ENDIAN_MARKER => Endian::Little,
+            marker if marker == ENDIAN_MARKER.swap_bytes() => Endian::Big,
+            _ => return Err(unexpected_at(input, 6, "Expected an endianness marker")),
+        };
+        let version = endian.read_u16(&header[4..]);
+        if version != HVB_VERSION {
+            return Err(DeserializationError::new(
+                ErrorKind::UnexpectedByte(header[4]),
+                format!("Unsupported .hvb version {version}, only {HVB_VERSION} is known"),
+            )
+            .at(input, 4));
+        }
+        let layout = match header[8] {
+            0 => Layout::Rows,
+            1 => Layout::Columns,
+            _ => return Err(unexpected_at(input, 8, "Expected a layout of 0 or 1")),
+        };
+        if let Some(i) = header[9..16].iter().position(|&b| b != 0) {
+            return Err(unexpected_at(
+                input,
+                9 + i,
+                "Reserved header bytes have to be 0",
+            ));
+        }
+
+        let count = endian.read_u64(&header[16..]);
+        let expected = usize::try_from(count)
+            .ok()
+            .and_then(|count| count.checked_mul(PAIR_LEN))
+            .and_then(|body| body.checked_add(HVB_HEADER_LEN + CHECKSUM_LEN));
+        if expected != Some(input.len()) {
+            let kind = match expected {
+                Some(expected) if expected < input.len() => {
+                    ErrorKind::UnexpectedByte(input[expected])
+                }
+                _ => ErrorKind::TruncatedInput,
+            };
+            let offset = expected.unwrap_or(input.len()).min(input.len());
+            return Err(DeserializationError::new(
+                kind,
+                format!("The header says {count} pairs, which doesn't match the file's length"),
+            )
+            .at(input, offset));
+        }
+        Ok(Self {
+            layout,
+            endian,
+            count: count as usize,
+            input,
+            body: &input[HVB_HEADER_LEN..input.len() - CHECKSUM_LEN],
+        })
+    }
+
+    /// Compares the trailing checksum to one of the rest of the file
+    pub fn verify(&self) -> Result<(), DeserializationError> {
+        let end = self.input.len() - CHECKSUM_LEN;
+        let mut checksum = Checksum::default();
+        checksum.update(&self.input[..end]);
+        let stored = self.endian.read_u64(&self.input[end..]);
+        if checksum.finish() != stored {
+            return Err(DeserializationError::new(
+                ErrorKind::BadChecksum,
+                format!(
+                    "The file's checksum is {:016x} but it says {:016x}",
+                    checksum.finish(),
+                    stored
+                ),
+            )
+            .at(self.input, end));
+        }
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// The coordinates in place, in the file's layout. Only there when the file is in the machine's byte
+    /// order and the body is 8 byte aligned, as it is in a mapping or any allocation of f64s
+    pub fn values(&self) -> Option<&'a [f64]> {
+        if self.endian != Endian::NATIVE {
+            return None;
+        }
+        // Every bit pattern is a valid f64, so reinterpreting aligned bytes is fine
+        match unsafe { self.body.align_to::<f64>() } {
+            ([], values, []) => Some(values),
+            _ => None,
+        }
+    }
+
+    #[inline(always)]
+    fn value(&self, index: usize) -> f64 {
+        self.endian.read_f64(&self.body[index * 8..])
+    }
+
+    pub fn pair(&self, index: usize) -> CoordPair {
+        let at = |i: usize| match self.layout {
+            Layout::Rows => self.value(index * 4 + i),
+            Layout::Columns => self.value(i * self.count + index),
+        };
+        CoordPair {
+            lat0: at(0),
+            lon0: at(1),
+            lat1: at(2),
+            lon1: at(3),
+        }
+    }
+
+    pub fn pairs(&self) -> impl Iterator<Item = CoordPair> + 'a {
+        let file = *self;
+        (0..file.count).map(move |i| file.pair(i))
+    }
+}
+
+/// Checks the header and checksum, then copies out every pair
+pub fn deserialize_hvb(input: &[u8]) -> Result<Vec<CoordPair>, DeserializationError> {
+    let file = HvbFile::new(input)?;
+    file.verify()?;
+    Ok(file.pairs().collect())
+}
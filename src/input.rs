@@ -0,0 +1,109 @@
+use std::{fs::File, io, ops::Deref};
+
+use clap::ValueEnum;
+
+// Ways of getting the input file in front of the parsers. Reading copies it through a buffer,
+// mapping lets the parsers work on the page cache directly and leaves the copying to page faults
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum InputSource {
+    /// Read the file through a buffer
+    Read,
+    /// Memory-map the file and parse the mapped bytes (Linux only)
+    Mmap,
+}
+
+/// `madvise` hints for a mapped input
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Advice {
+    /// MADV_SEQUENTIAL, read ahead aggressively and drop pages soon after they're used
+    Sequential,
+    /// MADV_HUGEPAGE, back the mapping with transparent huge pages where the kernel allows it
+    Hugepage,
+}
+
+/// Read-only private mapping of a whole file
+pub struct Mmap {
+    ptr: *const u8,
+    len: usize,
+}
+
+impl Mmap {
+    #[cfg(target_os = "linux")]
+    pub fn open(file: &File) -> io::Result<Self> {
+        let len = usize::try_from(file.metadata()?.len())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "file too large to map"))?;
+        // mmap rejects empty mappings
+        if len == 0 {
+            return Ok(Self {
+                ptr: std::ptr::NonNull::dangling().as_ptr(),
+                len,
+            });
+        }
+        let ptr = unsafe {
+            libc::mmap(
+                std::ptr::null_mut(),
+                len,
+                libc::PROT_READ,
+                libc::MAP_PRIVATE,
+                std::os::fd::AsRawFd::as_raw_fd(file),
+                0,
+            )
+        };
+        if ptr == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(Self {
+            ptr: ptr as *const u8,
+            len,
+        })
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub fn open(_file: &File) -> io::Result<Self> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "memory-mapped input is only supported on Linux",
+        ))
+    }
+
+    /// Passes a hint on to the kernel. Hints are best-effort, so callers are free to ignore the error
+    #[cfg(target_os = "linux")]
+    pub fn advise(&self, advice: Advice) -> io::Result<()> {
+        if self.len == 0 {
+            return Ok(());
+        }
+        let advice = match advice {
+            Advice::Sequential => libc::MADV_SEQUENTIAL,
+            Advice::Hugepage => libc::MADV_HUGEPAGE,
+        };
+        if unsafe { libc::madvise(self.ptr as *mut libc::c_void, self.len, advice) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub fn advise(&self, _advice: Advice) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Deref for Mmap {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        // The mapping lives as long as self. Writes to the file by other processes while it's mapped
+        // can still change these bytes underneath us, same as with any mmap based reader
+        unsafe { std::slice::from_raw_parts(self.ptr, self.len) }
+    }
+}
+
+impl Drop for Mmap {
+    fn drop(&mut self) {
+        #[cfg(target_os = "linux")]
+        if self.len != 0 {
+            unsafe { libc::munmap(self.ptr as *mut libc::c_void, self.len) };
+        }
+    }
+}
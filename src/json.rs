@@ -0,0 +1,429 @@
+use crate::{
+    error::{DeserializationError, ErrorKind},
+    float::parse_f64,
+};
+
+// General JSON document parser, for inputs that don't fit the flat pair schema the fast paths assume
+// Objects keep their members in document order, duplicates included, so callers can decide how to treat them
+
+// Nesting deeper than this is rejected instead of risking the stack. Debug builds take a few KB a level,
+// so this keeps well inside a 2MB thread stack
+const MAX_DEPTH: usize = 128;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum JsonValue {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<JsonValue>),
+    Object(Vec<(String, JsonValue)>),
+}
+
+impl JsonValue {
+    /// Member `key` of an object. With duplicate keys the last one wins, like the streaming parsers
+    pub fn get(&self, key: &str) -> Option<&JsonValue> {
+        self.as_object()?
+            .iter()
+            .rev()
+            .find(|(name, _)| name == key)
+            .map(|(_, val)| val)
+    }
+
+    /// Element `index` of an array
+    pub fn at(&self, index: usize) -> Option<&JsonValue> {
+        self.as_array()?.get(index)
+    }
+
+    /// RFC 6901 JSON pointer lookup, e.g. `/pairs/0/lat0`
+    pub fn pointer(&self, pointer: &str) -> Option<&JsonValue> {
+        if pointer.is_empty() {
+            return Some(self);
+        }
+        let mut cur = self;
+        for token in pointer.strip_prefix('/')?.split('/') {
+            let token = token.replace("~1", "/").replace("~0", "~");
+            cur = match cur {
+                JsonValue::Object(_) => cur.get(&token)?,
+                JsonValue::Array(items) => items.get(token.parse::<usize>().ok()?)?,
+                _ => return None,
+            };
+        }
+        Some(cur)
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            JsonValue::Number(val) => Some(*val),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            JsonValue::String(val) => Some(val),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            JsonValue::Bool(val) => Some(*val),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[JsonValue]> {
+        match self {
+            JsonValue::Array(items) => Some(items),
+            _ => None,
+        }
+    }
+
+    pub fn as_object(&self) -> Option<&[(String, JsonValue)]> {
+        match self {
+            JsonValue::Object(members) => Some(members),
+            _ => None,
+        }
+    }
+
+    pub fn is_null(&self) -> bool {
+        matches!(self, JsonValue::Null)
+    }
+
+    /// The byte a value like this one starts with in a document
+    pub(crate) fn leading_byte(&self) -> u8 {
+        match self {
+            JsonValue::Null => b'n',
+            JsonValue::Bool(true) => b't',
+            JsonValue::Bool(false) => b'f',
+            JsonValue::Number(val) => ryu::Buffer::new().format(*val).as_bytes()[0],
+            JsonValue::String(_) => b'"',
+            JsonValue::Array(_) => b'[',
+            JsonValue::Object(_) => b'{',
+        }
+    }
+}
+
+/// Parses a complete document. A leading UTF-8 BOM is skipped, anything but whitespace after the value is an error
+pub fn parse_json(input: &[u8]) -> Result<JsonValue, DeserializationError> {
+    let mut parser = JsonParser::new(input);
+    let value = parser.parse_value()?;
+    parser.skip_whitespace();
+    if parser.pos != input.len() {
+        return Err(parser.unexpected("Expected the end of the document"));
+    }
+    Ok(value)
+}
+
+/// Byte offset of element `index` of the pairs array, which is either the document itself or its `pairs` member.
+/// Only used to locate errors, so it reparses instead of having `parse_json` keep offsets around
+pub(crate) fn pair_offset(input: &[u8], index: usize) -> Option<usize> {
+    let mut parser = JsonParser::new(input);
+    parser.skip_whitespace();
+    if parser.peek() == Some(b'{') {
+        let mut pairs = None;
+        parser.pos += 1;
+        loop {
+            parser.skip_whitespace();
+            let key = parser.parse_string().ok()?;
+            parser.skip_whitespace();
+            parser.expect(b':').ok()?;
+            parser.skip_whitespace();
+            if key == "pairs" {
+                pairs = Some(parser.pos);
+            }
+            parser.parse_value().ok()?;
+            parser.skip_whitespace();
+            if parser.peek() != Some(b',') {
+                break;
+            }
+            parser.pos += 1;
+        }
+        parser.pos = pairs?;
+    }
+    parser.expect(b'[').ok()?;
+    for _ in 0..index {
+        parser.parse_value().ok()?;
+        parser.skip_whitespace();
+        parser.expect(b',').ok()?;
+    }
+    parser.skip_whitespace();
+    Some(parser.pos)
+}
+
+struct JsonParser<'a> {
+    input: &'a [u8],
+    pos: usize,
+    depth: usize,
+}
+
+impl<'a> JsonParser<'a> {
+    fn new(input: &'a [u8]) -> Self {
+        Self {
+            input,
+            pos: if input.starts_with(b"\xEF\xBB\xBF") {
+                3
+            } else {
+                0
+            },
+            depth: 0,
+        }
+    }
+
+    fn error_at(&self, kind: ErrorKind, msg: &str, offset: usize) -> DeserializationError {
+        DeserializationError::new(kind, msg).at(self.input, offset)
+    }
+
+    /// Error about whatever is at the current position: the byte there, or the end of the input
+    fn unexpected(&self, msg: &str) -> DeserializationError {
+        let kind = match self.peek() {
+            Some(b) => ErrorKind::UnexpectedByte(b),
+            None => ErrorKind::TruncatedInput,
+        };
+        self.error_at(kind, msg, self.pos)
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.input.get(self.pos).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(b' ' | b'\t' | b'\n' | b'\r') = self.peek() {
+            self.pos += 1;
+        }
+    }
+
+    fn expect(&mut self, byte: u8) -> Result<(), DeserializationError> {
+        if self.peek() != Some(byte) {
+            return Err(self.unexpected(&format!("Expected '{}'", byte as char)));
+        }
+        self.pos += 1;
+        Ok(())
+    }
+
+    fn parse_value(&mut self) -> Result<JsonValue, DeserializationError> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some(b'{') => self.nested(Self::parse_object),
+            Some(b'[') => self.nested(Self::parse_array),
+            Some(b'"') => Ok(JsonValue::String(self.parse_string()?)),
+            Some(b't') => self.parse_literal(b"true", JsonValue::Bool(true)),
+            Some(b'f') => self.parse_literal(b"false", JsonValue::Bool(false)),
+            Some(b'n') => self.parse_literal(b"null", JsonValue::Null),
+            Some(b'-' | b'0'..=b'9') => Ok(JsonValue::Number(self.parse_number()?)),
+            _ => Err(self.unexpected("Expected a value")),
+        }
+    }
+
+    fn nested(
+        &mut self,
+        parse: fn(&mut Self) -> Result<JsonValue, DeserializationError>,
+    ) -> Result<JsonValue, DeserializationError> {
+        if self.depth == MAX_DEPTH {
+            return Err(self.unexpected("Nesting too deep"));
+        }
+        self.depth += 1;
+        let value = parse(self);
+        self.depth -= 1;
+        value
+    }
+
+    fn parse_object(&mut self) -> Result<JsonValue, DeserializationError> {
+        self.expect(b'{')?;
+        let mut members = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some(b'}') {
+            self.pos += 1;
+            return Ok(JsonValue::Object(members));
+        }
+        loop {
+            self.skip_whitespace();
+            let key = self.parse_string()?;
+            self.skip_whitespace();
+            self.expect(b':')?;
+            let value = self.parse_value()?;
+            members.push((key, value));
+            self.skip_whitespace();
+            match self.peek() {
+                Some(b',') => self.pos += 1,
+                Some(b'}') => {
+                    self.pos += 1;
+                    return Ok(JsonValue::Object(members));
+                }
+                _ => return Err(self.unexpected("Expected ',' or '}' in object")),
+            }
+        }
+    }
+
+    fn parse_array(&mut self) -> Result<JsonValue, DeserializationError> {
+        self.expect(b'[')?;
+        let mut items = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some(b']') {
+            self.pos += 1;
+            return Ok(JsonValue::Array(items));
+        }
+        loop {
+            items.push(self.parse_value()?);
+            self.skip_whitespace();
+            match self.peek() {
+                Some(b',') => self.pos += 1,
+                Some(b']') => {
+                    self.pos += 1;
+                    return Ok(JsonValue::Array(items));
+                }
+                _ => return Err(self.unexpected("Expected ',' or ']' in array")),
+            }
+        }
+    }
+
+    fn parse_literal(
+        &mut self,
+        literal: &[u8],
+        value: JsonValue,
+    ) -> Result<JsonValue, DeserializationError> {
+        for &b in literal {
+            if self.peek() != Some(b) {
+                return Err(self.unexpected("Invalid literal"));
+            }
+            self.pos += 1;
+        }
+        Ok(value)
+    }
+
+    fn parse_number(&mut self) -> Result<f64, DeserializationError> {
+        let start = self.pos;
+        let bad_number = |p: &Self, msg: &str| p.error_at(ErrorKind::BadNumber, msg, p.pos);
+        let digits = |p: &mut Self| {
+            let from = p.pos;
+            while let Some(b'0'..=b'9') = p.peek() {
+                p.pos += 1;
+            }
+            p.pos - from
+        };
+        if self.peek() == Some(b'-') {
+            self.pos += 1;
+        }
+        match self.peek() {
+            Some(b'0') => self.pos += 1,
+            Some(b'1'..=b'9') => {
+                digits(self);
+            }
+            _ => return Err(bad_number(self, "Expected digits")),
+        }
+        if self.peek() == Some(b'.') {
+            self.pos += 1;
+            if digits(self) == 0 {
+                return Err(bad_number(self, "Expected digits after the decimal point"));
+            }
+        }
+        if let Some(b'e' | b'E') = self.peek() {
+            self.pos += 1;
+            if let Some(b'+' | b'-') = self.peek() {
+                self.pos += 1;
+            }
+            if digits(self) == 0 {
+                return Err(bad_number(self, "Expected digits in the exponent"));
+            }
+        }
+        parse_f64(&self.input[start..self.pos]).ok_or_else(|| {
+            self.error_at(
+                ErrorKind::BadNumber,
+                "Can't parse floating point value",
+                start,
+            )
+        })
+    }
+
+    fn parse_hex4(&mut self) -> Result<u32, DeserializationError> {
+        let mut out = 0;
+        for _ in 0..4 {
+            let digit = self
+                .peek()
+                .and_then(|b| (b as char).to_digit(16))
+                .ok_or_else(|| self.unexpected("Invalid unicode escape"))?;
+            out = out * 16 + digit;
+            self.pos += 1;
+        }
+        Ok(out)
+    }
+
+    fn parse_string(&mut self) -> Result<String, DeserializationError> {
+        self.expect(b'"')?;
+        let mut out = String::new();
+        loop {
+            let run_start = self.pos;
+            while let Some(b) = self.peek() {
+                if b == b'"' || b == b'\\' || b < 0x20 {
+                    break;
+                }
+                self.pos += 1;
+            }
+            let run = std::str::from_utf8(&self.input[run_start..self.pos]).map_err(|e| {
+                let offset = run_start + e.valid_up_to();
+                self.error_at(
+                    ErrorKind::UnexpectedByte(self.input[offset]),
+                    "Invalid UTF-8 in string",
+                    offset,
+                )
+            })?;
+            out.push_str(run);
+
+            match self.peek() {
+                Some(b'"') => {
+                    self.pos += 1;
+                    return Ok(out);
+                }
+                Some(b'\\') => {
+                    self.pos += 1;
+                    match self.peek() {
+                        Some(b'"') => out.push('"'),
+                        Some(b'\\') => out.push('\\'),
+                        Some(b'/') => out.push('/'),
+                        Some(b'b') => out.push('\u{8}'),
+                        Some(b'f') => out.push('\u{c}'),
+                        Some(b'n') => out.push('\n'),
+                        Some(b'r') => out.push('\r'),
+                        Some(b't') => out.push('\t'),
+                        Some(b'u') => {
+                            self.pos += 1;
+                            out.push(self.parse_unicode_escape()?);
+                            continue;
+                        }
+                        _ => return Err(self.unexpected("Invalid escape sequence")),
+                    }
+                    self.pos += 1;
+                }
+                Some(_) => return Err(self.unexpected("Unescaped control character in string")),
+                None => return Err(self.unexpected("Unterminated string")),
+            }
+        }
+    }
+
+    // After `\u`. Surrogate pairs have to come as two consecutive escapes
+    fn parse_unicode_escape(&mut self) -> Result<char, DeserializationError> {
+        let start = self.pos;
+        let first = self.parse_hex4()?;
+        let invalid = |p: &Self, msg: &str, offset: usize| {
+            p.error_at(ErrorKind::UnexpectedByte(p.input[offset]), msg, offset)
+        };
+        let code = match first {
+            0xD800..=0xDBFF => {
+                if !self.input[self.pos..].starts_with(b"\\u") {
+                    return Err(self.unexpected("Unpaired high surrogate"));
+                }
+                self.pos += 2;
+                let second_start = self.pos;
+                let second = self.parse_hex4()?;
+                if !(0xDC00..=0xDFFF).contains(&second) {
+                    return Err(invalid(self, "Invalid low surrogate", second_start));
+                }
+                0x10000 + ((first - 0xD800) << 10) + (second - 0xDC00)
+            }
+            0xDC00..=0xDFFF => return Err(invalid(self, "Unpaired low surrogate", start)),
+            code => code,
+        };
+        char::from_u32(code).ok_or_else(|| invalid(self, "Invalid unicode escape", start))
+    }
+}
@@ -1,14 +1,35 @@
 pub mod calc;
+pub mod error;
+pub mod float;
 pub mod generate;
+pub mod hvb;
+pub mod input;
+pub mod json;
+pub mod messy;
+pub mod metadata;
 pub mod metrics;
 pub mod parser;
+pub mod pretty;
+pub mod reference;
+pub mod region;
+pub mod rng;
+pub mod structural;
+pub mod tokenizer;
+pub mod track;
+pub mod values;
 
-#[derive(Debug)]
+// So the derives' `::haversine_calculator` paths resolve in here too
+extern crate self as haversine_calculator;
+
+/// Key order in the JSON follows the field order. `lon1` comes before `lat1` only so that the derived
+/// `Serializable` writes the keys in the order the generator always has, and files stay byte for byte the same.
+/// `Deserializable` is written out in the parser, to read records in place
+#[derive(Debug, parser::Serializable)]
 pub struct CoordPair {
     lat0: f64,
     lon0: f64,
-    lat1: f64,
     lon1: f64,
+    lat1: f64,
 }
 
 impl From<((f64, f64), (f64, f64))> for CoordPair {
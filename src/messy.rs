@@ -0,0 +1,261 @@
+use std::io::Write;
+
+use rand::RngCore;
+
+use crate::{
+    rng::{gen_index, gen_range, gen_unit},
+    CoordPair,
+};
+
+// Writes the same pairs as the json format, but with randomized layout and number spelling, for stressing the parsers
+// Every spelling is an exact rewrite of ryu's shortest representation, so each value still parses to the same f64
+
+const WHITESPACE: [&[u8]; 6] = [b" ", b"  ", b"\n", b"\r\n", b"\t", b"\n    "];
+
+const EXTRA_KEYS: [&str; 6] = ["id", "alt", "name", "source", "valid", "accuracy_m"];
+
+const ALPHANUMERIC: &[u8] = b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789";
+
+pub struct MessyJson<R> {
+    rng: R,
+    extra_keys: bool,
+}
+
+// A finite value as `digits * 10^exp`, with no leading or trailing zeros in `digits`
+struct Decimal {
+    negative: bool,
+    digits: Vec<u8>,
+    exp: i32,
+}
+
+impl Decimal {
+    fn new(val: f64) -> Self {
+        let mut buf = ryu::Buffer::new();
+        let s = buf.format_finite(val).as_bytes();
+        let negative = s[0] == b'-';
+        let s = if negative { &s[1..] } else { s };
+        let (mantissa, mut exp) = match s.iter().position(|&b| b == b'e') {
+            Some(pos) => (
+                &s[..pos],
+                std::str::from_utf8(&s[pos + 1..])
+                    .unwrap()
+                    .parse::<i32>()
+                    .unwrap(),
+            ),
+            None => (s, 0),
+        };
+        let mut digits = Vec::with_capacity(mantissa.len());
+        let mut seen_point = false;
+        for &b in mantissa {
+            if b == b'.' {
+                seen_point = true;
+            } else {
+                digits.push(b);
+                if seen_point {
+                    exp -= 1;
+                }
+            }
+        }
+        let leading = digits.iter().take_while(|&&b| b == b'0').count();
+        digits.drain(..leading);
+        while digits.last() == Some(&b'0') {
+            digits.pop();
+            exp += 1;
+        }
+        Self {
+            negative,
+            digits,
+            exp,
+        }
+    }
+}
+
+impl<R: RngCore> MessyJson<R> {
+    pub fn new(rng: R, extra_keys: bool) -> Self {
+        Self { rng, extra_keys }
+    }
+
+    pub fn write_pairs(
+        &mut self,
+        mut pairs: impl Iterator<Item = CoordPair>,
+        writer: &mut impl Write,
+    ) -> Result<(), std::io::Error> {
+        self.write_whitespace(writer)?;
+        writer.write_all(b"[")?;
+        if let Some(item) = pairs.next() {
+            self.write_whitespace(writer)?;
+            self.write_pair(&item, writer)?;
+        }
+        for item in pairs {
+            self.write_whitespace(writer)?;
+            writer.write_all(b",")?;
+            self.write_whitespace(writer)?;
+            self.write_pair(&item, writer)?;
+        }
+        self.write_whitespace(writer)?;
+        writer.write_all(b"]")?;
+        self.write_whitespace(writer)
+    }
+
+    fn write_pair(
+        &mut self,
+        pair: &CoordPair,
+        writer: &mut impl Write,
+    ) -> Result<(), std::io::Error> {
+        let mut members: Vec<(&str, Option<f64>)> = vec![
+            ("lat0", Some(pair.lat0)),
+            ("lon0", Some(pair.lon0)),
+            ("lat1", Some(pair.lat1)),
+            ("lon1", Some(pair.lon1)),
+        ];
+        if self.extra_keys {
+            for _ in 0..gen_index(&mut self.rng, 3) {
+                let key = EXTRA_KEYS[gen_index(&mut self.rng, EXTRA_KEYS.len())];
+                if members.iter().all(|(name, _)| *name != key) {
+                    members.push((key, None));
+                }
+            }
+        }
+        for i in (1..members.len()).rev() {
+            members.swap(i, gen_index(&mut self.rng, i + 1));
+        }
+
+        writer.write_all(b"{")?;
+        for (i, (key, val)) in members.into_iter().enumerate() {
+            self.write_whitespace(writer)?;
+            if i != 0 {
+                writer.write_all(b",")?;
+                self.write_whitespace(writer)?;
+            }
+            write!(writer, "\"{}\"", key)?;
+            self.write_whitespace(writer)?;
+            writer.write_all(b":")?;
+            self.write_whitespace(writer)?;
+            match val {
+                Some(val) => self.write_number(val, writer)?,
+                None => self.write_extra_value(writer)?,
+            }
+        }
+        self.write_whitespace(writer)?;
+        writer.write_all(b"}")
+    }
+
+    fn write_whitespace(&mut self, writer: &mut impl Write) -> Result<(), std::io::Error> {
+        for _ in 0..gen_index(&mut self.rng, 3) {
+            writer.write_all(WHITESPACE[gen_index(&mut self.rng, WHITESPACE.len())])?;
+        }
+        Ok(())
+    }
+
+    fn write_number(&mut self, val: f64, writer: &mut impl Write) -> Result<(), std::io::Error> {
+        let dec = Decimal::new(val);
+        if dec.negative {
+            writer.write_all(b"-")?;
+        }
+        if dec.digits.is_empty() {
+            let zero: &[u8] = match gen_index(&mut self.rng, 5) {
+                0 => b"0",
+                1 => b"0.0",
+                2 => b"0.000",
+                3 => b"0e0",
+                _ => b"0.0E+00",
+            };
+            // Negative zero only appears as a spelling of zero itself, since -0.0 == 0.0
+            if !dec.negative && gen_index(&mut self.rng, 4) == 0 {
+                writer.write_all(b"-")?;
+            }
+            return writer.write_all(zero);
+        }
+        let n = dec.digits.len() as i32;
+        match gen_index(&mut self.rng, 4) {
+            // Plain decimal
+            0 => self.write_plain(&dec.digits, n + dec.exp, writer),
+            // Normalized scientific, d.ddd e x
+            1 => {
+                self.write_plain(&dec.digits, 1, writer)?;
+                self.write_exponent(dec.exp + n - 1, writer)
+            }
+            // Leading zero fraction, 0.ddd e x
+            2 => {
+                self.write_plain(&dec.digits, 0, writer)?;
+                self.write_exponent(dec.exp + n, writer)
+            }
+            // Integer mantissa, ddd e x
+            _ => {
+                writer.write_all(&dec.digits)?;
+                self.write_exponent(dec.exp, writer)
+            }
+        }
+    }
+
+    // Writes `digits` with the decimal point after `point` digits, padding with zeros as needed
+    fn write_plain(
+        &mut self,
+        digits: &[u8],
+        point: i32,
+        writer: &mut impl Write,
+    ) -> Result<(), std::io::Error> {
+        let n = digits.len() as i32;
+        if point <= 0 {
+            writer.write_all(b"0.")?;
+            for _ in 0..-point {
+                writer.write_all(b"0")?;
+            }
+            writer.write_all(digits)?;
+        } else if point >= n {
+            writer.write_all(digits)?;
+            for _ in 0..point - n {
+                writer.write_all(b"0")?;
+            }
+            if gen_index(&mut self.rng, 2) == 0 {
+                return Ok(());
+            }
+            writer.write_all(b".0")?;
+        } else {
+            writer.write_all(&digits[..point as usize])?;
+            writer.write_all(b".")?;
+            writer.write_all(&digits[point as usize..])?;
+        }
+        for _ in 0..gen_index(&mut self.rng, 4) {
+            writer.write_all(b"0")?;
+        }
+        Ok(())
+    }
+
+    fn write_exponent(&mut self, exp: i32, writer: &mut impl Write) -> Result<(), std::io::Error> {
+        writer.write_all(if gen_index(&mut self.rng, 2) == 0 {
+            b"e"
+        } else {
+            b"E"
+        })?;
+        if exp < 0 {
+            writer.write_all(b"-")?;
+        } else if gen_index(&mut self.rng, 2) == 0 {
+            writer.write_all(b"+")?;
+        }
+        for _ in 0..gen_index(&mut self.rng, 3) {
+            writer.write_all(b"0")?;
+        }
+        write!(writer, "{}", exp.unsigned_abs())
+    }
+
+    fn write_extra_value(&mut self, writer: &mut impl Write) -> Result<(), std::io::Error> {
+        match gen_index(&mut self.rng, 5) {
+            0 => writer.write_all(b"null"),
+            1 => writer.write_all(b"true"),
+            2 => writer.write_all(b"false"),
+            3 => {
+                let val = gen_range(&mut self.rng, -1000.0, 1000.0);
+                self.write_number(val, writer)
+            }
+            _ => {
+                writer.write_all(b"\"")?;
+                for _ in 0..(gen_unit(&mut self.rng) * 12.0) as usize {
+                    let c = ALPHANUMERIC[gen_index(&mut self.rng, ALPHANUMERIC.len())];
+                    writer.write_all(&[c])?;
+                }
+                writer.write_all(b"\"")
+            }
+        }
+    }
+}
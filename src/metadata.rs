@@ -0,0 +1,259 @@
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader, Read, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::{generate::Cluster, region::BoundingBox};
+
+// Sidecar written next to generated files, recording how they were produced and what they should contain
+// Plain `key = value` lines, so it stays readable and diffable without a JSON parser
+
+pub const GENERATOR_ALGORITHM: &str = "xoshiro256**/splitmix64";
+
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// 64-bit FNV-1a over everything passed to `update`
+#[derive(Debug, Clone, Copy)]
+pub struct Checksum(u64);
+
+impl Default for Checksum {
+    fn default() -> Self {
+        Self(FNV_OFFSET)
+    }
+}
+
+impl Checksum {
+    pub fn update(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.0 = (self.0 ^ b as u64).wrapping_mul(FNV_PRIME);
+        }
+    }
+
+    pub fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+/// Counts and checksums everything written through it
+pub struct ChecksumWriter<W> {
+    inner: W,
+    checksum: Checksum,
+    bytes: u64,
+}
+
+impl<W: Write> ChecksumWriter<W> {
+    pub fn new(inner: W) -> Self {
+        Self {
+            inner,
+            checksum: Checksum::default(),
+            bytes: 0,
+        }
+    }
+
+    pub fn bytes(&self) -> u64 {
+        self.bytes
+    }
+
+    pub fn checksum(&self) -> u64 {
+        self.checksum.finish()
+    }
+}
+
+impl<W: Write> Write for ChecksumWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.checksum.update(&buf[..written]);
+        self.bytes += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Counts and checksums everything read through it
+pub struct ChecksumReader<R> {
+    inner: R,
+    checksum: Checksum,
+    bytes: u64,
+}
+
+impl<R: Read> ChecksumReader<R> {
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            checksum: Checksum::default(),
+            bytes: 0,
+        }
+    }
+
+    pub fn bytes(&self) -> u64 {
+        self.bytes
+    }
+
+    pub fn checksum(&self) -> u64 {
+        self.checksum.finish()
+    }
+}
+
+impl<R: Read> Read for ChecksumReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.checksum.update(&buf[..read]);
+        self.bytes += read as u64;
+        Ok(read)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GenerationMetadata {
+    pub version: String,
+    pub algorithm: String,
+    pub seed: u64,
+    pub count: usize,
+    pub mode: String,
+    /// Generator settings in the order they were given, e.g. `format`, `cluster_shape`
+    pub params: Vec<(String, String)>,
+    /// Centre and `min_lat,min_lon,max_lat,max_lon` bounds of each cluster
+    pub clusters: Vec<((f64, f64), BoundingBox)>,
+    pub bytes: u64,
+    pub checksum: u64,
+}
+
+impl GenerationMetadata {
+    pub fn new(seed: u64, count: usize, mode: &str) -> Self {
+        Self {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            algorithm: GENERATOR_ALGORITHM.to_string(),
+            seed,
+            count,
+            mode: mode.to_string(),
+            params: Vec::new(),
+            clusters: Vec::new(),
+            bytes: 0,
+            checksum: 0,
+        }
+    }
+
+    pub fn param(&mut self, key: &str, val: impl ToString) {
+        self.params.push((key.to_string(), val.to_string()));
+    }
+
+    pub fn record_clusters(&mut self, clusters: &[Cluster]) {
+        self.clusters = clusters.iter().map(|c| (c.center, c.bounds)).collect();
+    }
+
+    /// `input.json` -> `input.json.meta`
+    pub fn sidecar_path(path: &Path) -> PathBuf {
+        let mut name = path.as_os_str().to_owned();
+        name.push(".meta");
+        PathBuf::from(name)
+    }
+
+    pub fn write(&self, writer: &mut impl Write) -> io::Result<()> {
+        writeln!(writer, "version = {}", self.version)?;
+        writeln!(writer, "algorithm = {}", self.algorithm)?;
+        writeln!(writer, "seed = {}", self.seed)?;
+        writeln!(writer, "count = {}", self.count)?;
+        writeln!(writer, "mode = {}", self.mode)?;
+        for (key, val) in &self.params {
+            writeln!(writer, "param.{} = {}", key, val)?;
+        }
+        for ((lat, lon), b) in &self.clusters {
+            writeln!(
+                writer,
+                "cluster = {},{} {},{},{},{}",
+                lat, lon, b.min_lat, b.min_lon, b.max_lat, b.max_lon
+            )?;
+        }
+        writeln!(writer, "bytes = {}", self.bytes)?;
+        writeln!(writer, "checksum = fnv1a64:{:016x}", self.checksum)
+    }
+
+    pub fn read(reader: &mut impl BufRead) -> Result<Self, String> {
+        let mut out = Self::new(0, 0, "");
+        let mut seen_checksum = false;
+        for (i, line) in reader.lines().enumerate() {
+            let line = line.map_err(|e| e.to_string())?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let (key, val) = line
+                .split_once(" = ")
+                .ok_or_else(|| format!("line {}: expected 'key = value'", i + 1))?;
+            let invalid = || format!("line {}: invalid value for {}", i + 1, key);
+            match key {
+                "version" => out.version = val.to_string(),
+                "algorithm" => out.algorithm = val.to_string(),
+                "seed" => out.seed = val.parse().map_err(|_| invalid())?,
+                "count" => out.count = val.parse().map_err(|_| invalid())?,
+                "mode" => out.mode = val.to_string(),
+                "bytes" => out.bytes = val.parse().map_err(|_| invalid())?,
+                "checksum" => {
+                    let hex = val
+                        .strip_prefix("fnv1a64:")
+                        .ok_or_else(|| format!("line {}: unknown checksum kind", i + 1))?;
+                    out.checksum = u64::from_str_radix(hex, 16).map_err(|_| invalid())?;
+                    seen_checksum = true;
+                }
+                "cluster" => {
+                    let nums = val
+                        .split([' ', ','])
+                        .map(str::parse::<f64>)
+                        .collect::<Result<Vec<_>, _>>()
+                        .map_err(|_| invalid())?;
+                    let [lat, lon, min_lat, min_lon, max_lat, max_lon] = nums[..] else {
+                        return Err(format!("line {}: expected 6 cluster values", i + 1));
+                    };
+                    out.clusters.push((
+                        (lat, lon),
+                        BoundingBox {
+                            min_lat,
+                            max_lat,
+                            min_lon,
+                            max_lon,
+                        },
+                    ));
+                }
+                _ => match key.strip_prefix("param.") {
+                    Some(param) => out.params.push((param.to_string(), val.to_string())),
+                    None => return Err(format!("line {}: unknown key {}", i + 1, key)),
+                },
+            }
+        }
+        if !seen_checksum {
+            return Err("metadata has no checksum".to_string());
+        }
+        Ok(out)
+    }
+
+    /// Reads the sidecar of `path`, if there is one
+    pub fn load_for(path: &Path) -> Result<Option<Self>, String> {
+        let file = match File::open(Self::sidecar_path(path)) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.to_string()),
+        };
+        Self::read(&mut BufReader::new(file)).map(Some)
+    }
+
+    /// Compares what was read from the data file against the recorded size and checksum
+    pub fn verify(&self, bytes: u64, checksum: u64) -> Result<(), String> {
+        if bytes != self.bytes {
+            return Err(format!(
+                "input is {} bytes but its metadata records {}, the file was truncated or modified",
+                bytes, self.bytes
+            ));
+        }
+        if checksum != self.checksum {
+            return Err(format!(
+                "input checksum {:016x} doesn't match the recorded {:016x}, the file was modified",
+                checksum, self.checksum
+            ));
+        }
+        Ok(())
+    }
+}
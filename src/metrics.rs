@@ -48,7 +48,11 @@ impl Drop for Benchmark {
 
         let mut total_cpu_used = 0;
         unsafe {
-            for item in BENCHMARK_ANCHOR.data.iter().filter(|item| item.0 != 0) {
+            for item in (*std::ptr::addr_of!(BENCHMARK_ANCHOR))
+                .data
+                .iter()
+                .filter(|item| item.0 != 0)
+            {
                 let entry = bench_map.entry(item.3).or_insert((0, 0, 0));
                 entry.0 += item.0;
                 entry.1 += item.1;
@@ -1,86 +1,618 @@
 use std::{
-    error::Error,
-    fmt::Display,
     io::{BufRead, Write},
+    marker::PhantomData,
 };
 
+use clap::ValueEnum;
 use rand::RngCore;
 
-use crate::{bench_block, generate::CoordPairGen, metrics::record_bytes, CoordPair};
+use crate::{
+    bench_block,
+    float::{is_json_number, parse_f64},
+    generate::CoordPairGen,
+    hvb::{deserialize_hvb, write_hvb, Endian, Layout, HVB_MAGIC},
+    json::{pair_offset, parse_json, JsonValue},
+    metrics::record_bytes,
+    reference::{deserialize_reference, looks_like_reference, write_reference},
+    tokenizer::{Member, RecordEnd, Tokenizer},
+    CoordPair,
+};
+
+pub use crate::error::{DeserializationError, ErrorKind, Position};
+pub use json_derive::{Deserializable, Serializable};
 
 /// Pretty much serde without the intermediate representation
+pub trait Deserializable
+where
+    Self: Sized,
+{
+    fn streaming_deserialize(reader: &mut impl BufRead) -> Result<Self, DeserializationError>;
 
-#[derive(Debug)]
-pub struct DeserializationError(String);
+    /// How sequences and derived structs read the values in them. Errors are positioned in the
+    /// tokenizer's whole input. Types that can work on its buffered tokens directly override this
+    fn tokenized_deserialize<R: BufRead>(
+        reader: &mut Tokenizer<R>,
+    ) -> Result<Self, DeserializationError> {
+        let start = reader.position();
+        Self::streaming_deserialize(reader).map_err(|err| err.after(start))
+    }
+}
 
-impl From<std::io::Error> for DeserializationError {
-    fn from(value: std::io::Error) -> Self {
-        Self(value.to_string())
+/// Offset of `inner` within `outer`, which it has to be a subslice of
+fn offset_in(outer: &[u8], inner: &[u8]) -> usize {
+    inner.as_ptr() as usize - outer.as_ptr() as usize
+}
+
+/// Error about the byte at `offset`, or about the input ending there
+pub(crate) fn unexpected_at(input: &[u8], offset: usize, msg: &str) -> DeserializationError {
+    let kind = match input.get(offset) {
+        Some(&b) => ErrorKind::UnexpectedByte(b),
+        None => ErrorKind::TruncatedInput,
+    };
+    DeserializationError::new(kind, msg).at(input, offset)
+}
+
+/// How the JSON parsers treat records that don't match the schema exactly
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ParseOptions {
+    /// Reject members a record doesn't have, repeated members and values that aren't plain numbers.
+    /// Otherwise unknown members are skipped, the last of repeated ones wins, and the tree parser
+    /// accepts numbers in strings
+    pub strict: bool,
+}
+
+impl ParseOptions {
+    pub fn strict() -> Self {
+        Self { strict: true }
     }
 }
 
-impl Display for DeserializationError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.0)
+/// What the streaming parser went through. Record lengths run from the first byte of a record to its last,
+/// without the whitespace and separators around it
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ParseStats {
+    pub records: u64,
+    /// Everything consumed, brackets, separators and whitespace included
+    pub bytes: u64,
+    pub min_record_len: u64,
+    pub max_record_len: u64,
+    /// The lengths of all the records added up
+    pub record_bytes: u64,
+    /// Whitespace between tokens, nested arrays included but not the insides of skipped values
+    pub whitespace_bytes: u64,
+    /// Members that aren't part of the record and were skipped
+    pub ignored_keys: u64,
+}
+
+impl ParseStats {
+    pub fn mean_record_len(&self) -> f64 {
+        match self.records {
+            0 => 0.0,
+            records => self.record_bytes as f64 / records as f64,
+        }
+    }
+
+    pub(crate) fn add_record(&mut self, len: u64) {
+        if self.records == 0 || len < self.min_record_len {
+            self.min_record_len = len;
+        }
+        self.max_record_len = self.max_record_len.max(len);
+        self.record_bytes += len;
+        self.records += 1;
+    }
+
+    /// Takes in what a nested array's reader counted, which is part of the enclosing record
+    pub(crate) fn add_nested(&mut self, nested: ParseStats) {
+        self.whitespace_bytes += nested.whitespace_bytes;
+        self.ignored_keys += nested.ignored_keys;
     }
 }
 
-impl Error for DeserializationError {}
+/// Records recovery mode skipped over, with the errors of the first few
+#[derive(Debug, Default)]
+pub struct Skipped {
+    pub count: u64,
+    /// In the order they were found, at most `max_diagnostics` of them
+    pub diagnostics: Vec<DeserializationError>,
+    pub max_diagnostics: usize,
+}
 
-pub trait Deserializable
-where
-    Self: Sized,
-{
-    fn streaming_deserialize(reader: &mut impl BufRead) -> Result<Self, DeserializationError>;
+impl Skipped {
+    pub fn new(max_diagnostics: usize) -> Self {
+        Self {
+            max_diagnostics,
+            ..Self::default()
+        }
+    }
+
+    pub(crate) fn add(&mut self, err: DeserializationError) {
+        self.count += 1;
+        if self.diagnostics.len() < self.max_diagnostics {
+            self.diagnostics.push(err);
+        }
+    }
 }
 
-pub fn deserialize_single_pass(mut s: &str) -> Result<Vec<CoordPair>, DeserializationError> {
-    if &s[..2] != "[{" || &s[s.len() - 2..] != "}]" {
-        return Err(DeserializationError(
-            "Unexpected opening character".to_string(),
-        ));
+/// The members of a pair as its record is read, for the parsers that match keys themselves
+#[derive(Default)]
+pub(crate) struct PairMembers {
+    lat0: Option<f64>,
+    lon0: Option<f64>,
+    lat1: Option<f64>,
+    lon1: Option<f64>,
+}
+
+impl PairMembers {
+    /// Where the value of member `key` goes, `None` for a member to skip.
+    /// Errors are left for the caller to place on the key
+    pub(crate) fn slot(
+        &mut self,
+        key: &[u8],
+        options: ParseOptions,
+    ) -> Result<Option<&mut Option<f64>>, DeserializationError> {
+        let slot = match key {
+            b"lat0" => &mut self.lat0,
+            b"lon0" => &mut self.lon0,
+            b"lat1" => &mut self.lat1,
+            b"lon1" => &mut self.lon1,
+            _ if options.strict => {
+                return Err(DeserializationError::unknown_member(
+                    &String::from_utf8_lossy(key),
+                ))
+            }
+            _ => return Ok(None),
+        };
+        if options.strict && slot.is_some() {
+            return Err(DeserializationError::duplicate_member(
+                &String::from_utf8_lossy(key),
+            ));
+        }
+        Ok(Some(slot))
+    }
+
+    /// The pair, or the error `missing` makes for the first member that never showed up
+    pub(crate) fn finish(
+        self,
+        missing: impl Fn(&str) -> DeserializationError,
+    ) -> Result<CoordPair, DeserializationError> {
+        Ok(CoordPair {
+            lat0: self.lat0.ok_or_else(|| missing("lat0"))?,
+            lon0: self.lon0.ok_or_else(|| missing("lon0"))?,
+            lat1: self.lat1.ok_or_else(|| missing("lat1"))?,
+            lon1: self.lon1.ok_or_else(|| missing("lon1"))?,
+        })
+    }
+}
+
+pub(crate) fn missing_member(name: &str) -> DeserializationError {
+    DeserializationError::new(
+        ErrorKind::MissingMember(name.to_string()),
+        "Pairs need lat0, lon0, lat1 and lon1",
+    )
+}
+
+pub(crate) fn bad_number() -> DeserializationError {
+    DeserializationError::new(ErrorKind::BadNumber, "Can't parse floating point value")
+}
+
+/// `parse_f64`, which parsing strictly only gets numbers spelled the way JSON has them
+pub(crate) fn parse_number(token: &[u8], options: ParseOptions) -> Option<f64> {
+    if options.strict && !is_json_number(token) {
+        return None;
+    }
+    parse_f64(token)
+}
+
+/// Parses a JSON array of pairs in one pass over the bytes, in any layout: whitespace between tokens,
+/// CRLF line endings and a leading BOM are all fine. Every member has to have a number value
+pub fn deserialize_single_pass(input: &str) -> Result<Vec<CoordPair>, DeserializationError> {
+    deserialize_single_pass_with(input, ParseOptions::default())
+}
+
+pub fn deserialize_single_pass_with(
+    input: &str,
+    options: ParseOptions,
+) -> Result<Vec<CoordPair>, DeserializationError> {
+    let (start, end) = single_pass_body(input.as_bytes())?;
+    let mut out = Vec::with_capacity((end - start) / 100);
+    SinglePass::new(input.as_bytes(), start, options).records(end, &mut out)?;
+    Ok(out)
+}
+
+/// The range between the opening `[` and closing `]`
+fn single_pass_body(bytes: &[u8]) -> Result<(usize, usize), DeserializationError> {
+    let mut open = SinglePass::new(bytes, 0, ParseOptions::default());
+    if bytes.starts_with(b"\xEF\xBB\xBF") {
+        open.pos = 3;
+    }
+    open.skip_whitespace();
+    if open.peek() != Some(b'[') {
+        return Err(unexpected_at(bytes, open.pos, "Expected '['"));
+    }
+    let end = bytes.trim_ascii_end().len();
+    if end <= open.pos || bytes[end - 1] != b']' {
+        return Err(unexpected_at(bytes, end.max(open.pos + 1), "Expected ']'"));
+    }
+    Ok((open.pos + 1, end - 1))
+}
+
+struct SinglePass<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    options: ParseOptions,
+}
+
+impl<'a> SinglePass<'a> {
+    fn new(bytes: &'a [u8], pos: usize, options: ParseOptions) -> Self {
+        Self {
+            bytes,
+            pos,
+            options,
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.pos).copied()
+    }
+
+    #[inline(always)]
+    fn skip_whitespace(&mut self) {
+        while let Some(b' ' | b'\n' | b'\r' | b'\t') = self.peek() {
+            self.pos += 1;
+        }
+    }
+
+    fn expect(&mut self, byte: u8, msg: &str) -> Result<(), DeserializationError> {
+        self.skip_whitespace();
+        if self.peek() != Some(byte) {
+            return Err(unexpected_at(self.bytes, self.pos, msg));
+        }
+        self.pos += 1;
+        Ok(())
+    }
+
+    /// `,` separated records up to `end`, which is where the array's `]` is, or a chunk of it ends
+    fn records(
+        &mut self,
+        end: usize,
+        out: &mut Vec<CoordPair>,
+    ) -> Result<(), DeserializationError> {
+        self.skip_whitespace();
+        if self.pos == end {
+            return Ok(());
+        }
+        loop {
+            let record = out.len();
+            out.push(self.record().map_err(|err| err.in_record(record))?);
+            // Past the `]`, or past the end of a chunk that was cut inside a value
+            if self.pos > end {
+                return Err(
+                    unexpected_at(self.bytes, end, "Expected the end of the records")
+                        .in_record(record),
+                );
+            }
+            self.skip_whitespace();
+            if self.pos >= end {
+                return Ok(());
+            }
+            self.expect(b',', "Expected ',' or ']'")
+                .map_err(|err| err.in_record(record))?;
+        }
     }
-    s = &s[2..s.len() - 2];
-    let mut out = Vec::with_capacity(s.len() / 100);
-    for item in s.split("},{") {
-        let mut lat0: Option<f64> = None;
-        let mut lon0: Option<f64> = None;
-        let mut lat1: Option<f64> = None;
-        let mut lon1: Option<f64> = None;
-        for item in item.split(',') {
-            let (key, val) = item
-                .split_once(':')
-                .ok_or(DeserializationError("Invalid json".to_string()))?;
-            let val = val.parse::<f64>().map_err(|_| {
-                DeserializationError("Can't parse floating point value".to_string())
-            })?;
-            match key {
-                "\"lat0\"" => lat0 = Some(val),
-                "\"lon0\"" => lon0 = Some(val),
-                "\"lat1\"" => lat1 = Some(val),
-                "\"lon1\"" => lon1 = Some(val),
+
+    fn record(&mut self) -> Result<CoordPair, DeserializationError> {
+        self.expect(b'{', "Expected '{'")?;
+        let start = self.pos - 1;
+        let mut members = PairMembers::default();
+        self.skip_whitespace();
+        if self.peek() == Some(b'}') {
+            self.pos += 1;
+        } else {
+            loop {
+                let key = self.key()?;
+                let slot = members
+                    .slot(key, self.options)
+                    .map_err(|err| err.at(self.bytes, offset_in(self.bytes, key)))?;
+                self.expect(b':', "Expected ':' after the member key")?;
+                match slot {
+                    Some(slot) => *slot = Some(self.number()?),
+                    // Only left when not strict, unknown members are an error otherwise
+                    None => self.skip_value()?,
+                }
+                self.skip_whitespace();
+                match self.peek() {
+                    Some(b',') => self.pos += 1,
+                    Some(b'}') => {
+                        self.pos += 1;
+                        break;
+                    }
+                    _ => return Err(unexpected_at(self.bytes, self.pos, "Expected ',' or '}'")),
+                }
+            }
+        }
+
+        members.finish(|name| missing_member(name).at(self.bytes, start))
+    }
+
+    /// The raw bytes between the quotes of a key, or of a string value being skipped
+    fn key(&mut self) -> Result<&'a [u8], DeserializationError> {
+        self.expect(b'"', "Expected a member key")?;
+        let start = self.pos;
+        let mut escaped = false;
+        for (i, &b) in self.bytes[start..].iter().enumerate() {
+            match b {
+                _ if escaped => escaped = false,
+                b'\\' => escaped = true,
+                b'"' => {
+                    self.pos = start + i + 1;
+                    return Ok(&self.bytes[start..start + i]);
+                }
                 _ => {}
             }
         }
+        Err(unexpected_at(
+            self.bytes,
+            self.bytes.len(),
+            "Expected the closing '\"'",
+        ))
+    }
 
-        let cp = CoordPair {
-            lat0: lat0.ok_or_else(|| DeserializationError("member lat0 missing".to_owned()))?,
-            lon0: lon0.ok_or_else(|| DeserializationError("member lon0 missing".to_owned()))?,
-            lat1: lat1.ok_or_else(|| DeserializationError("member lat1 missing".to_owned()))?,
-            lon1: lon1.ok_or_else(|| DeserializationError("member lon1 missing".to_owned()))?,
-        };
-        out.push(cp);
+    /// Steps over the scalar at `pos`, a number or a literal like `true`, and returns where it starts
+    fn token(&mut self, msg: &str) -> Result<usize, DeserializationError> {
+        self.skip_whitespace();
+        let start = self.pos;
+        let len = self.bytes[start..]
+            .iter()
+            .position(|&b| matches!(b, b',' | b'}' | b']' | b' ' | b'\n' | b'\r' | b'\t'))
+            .unwrap_or(self.bytes.len() - start);
+        if len == 0 {
+            return Err(unexpected_at(self.bytes, start, msg));
+        }
+        self.pos += len;
+        Ok(start)
+    }
+
+    fn number(&mut self) -> Result<f64, DeserializationError> {
+        let start = self.token("Expected a number")?;
+        parse_number(&self.bytes[start..self.pos], self.options)
+            .ok_or_else(|| bad_number().at(self.bytes, start))
+    }
+
+    /// Steps over the value of a member that isn't read, whatever it is. Objects and arrays are matched
+    /// up to their closing bracket without being parsed, like `Tokenizer::skip_value` does
+    fn skip_value(&mut self) -> Result<(), DeserializationError> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some(b'"') => self.key().map(|_| ()),
+            Some(b'{' | b'[') => {
+                let (mut depth, mut in_string, mut escaped) = (0usize, false, false);
+                for (i, &b) in self.bytes[self.pos..].iter().enumerate() {
+                    match b {
+                        _ if escaped => escaped = false,
+                        b'\\' if in_string => escaped = true,
+                        b'"' => in_string = !in_string,
+                        _ if in_string => {}
+                        b'{' | b'[' => depth += 1,
+                        b'}' | b']' => {
+                            depth -= 1;
+                            if depth == 0 {
+                                self.pos += i + 1;
+                                return Ok(());
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+                Err(unexpected_at(
+                    self.bytes,
+                    self.bytes.len(),
+                    "Expected the end of the value",
+                ))
+            }
+            _ => self.token("Expected a value").map(|_| ()),
+        }
+    }
+}
+
+// Below this a chunk isn't worth a thread
+const MIN_PARALLEL_CHUNK: usize = 1 << 20;
+
+/// Where the record boundary at or after `from` is: the end of a `}`, whitespace, `,`, whitespace, `{`
+/// sequence. Returns the end of the first record and the start of the next
+fn next_boundary(bytes: &[u8], from: usize, end: usize) -> Option<(usize, usize)> {
+    let mut pos = from;
+    while let Some(close) = bytes[pos..end].iter().position(|&b| b == b'}') {
+        let record_end = pos + close + 1;
+        let mut scan = SinglePass::new(&bytes[..end], record_end, ParseOptions::default());
+        scan.skip_whitespace();
+        if scan.peek() == Some(b',') {
+            scan.pos += 1;
+            scan.skip_whitespace();
+            if scan.peek() == Some(b'{') {
+                return Some((record_end, scan.pos));
+            }
+        }
+        pos = record_end;
+    }
+    None
+}
+
+/// `deserialize_single_pass` split across up to `threads` threads. The body is cut into equal byte ranges
+/// and every cut is moved forward to the next record boundary, a `}` and a `{` with only a `,` and
+/// whitespace between them, so each chunk holds whole records. That sequence can also show up inside a
+/// key, or a string or nested value of a member that's skipped. A cut there leaves the chunk before it
+/// ending halfway through a record, so from the first chunk that fails the rest is read in one go again.
+/// The pairs and the first error, with its global record index, are the same as the single threaded parser's
+pub fn deserialize_parallel(
+    input: &str,
+    threads: usize,
+) -> Result<Vec<CoordPair>, DeserializationError> {
+    deserialize_parallel_with(input, threads, ParseOptions::default())
+}
+
+pub fn deserialize_parallel_with(
+    input: &str,
+    threads: usize,
+    options: ParseOptions,
+) -> Result<Vec<CoordPair>, DeserializationError> {
+    let bytes = input.as_bytes();
+    let (start, end) = single_pass_body(bytes)?;
+    let chunks = threads.min((end - start) / MIN_PARALLEL_CHUNK).max(1);
+    // (start, end) of each chunk's records
+    let mut ranges = vec![];
+    let mut chunk_start = start;
+    for i in 1..chunks {
+        let from = (start + (end - start) * i / chunks).max(chunk_start);
+        if let Some((record_end, next_start)) = next_boundary(bytes, from, end) {
+            ranges.push((chunk_start, record_end));
+            chunk_start = next_start;
+        }
+    }
+    ranges.push((chunk_start, end));
+
+    let results: Vec<_> = std::thread::scope(|scope| {
+        let handles: Vec<_> = ranges
+            .iter()
+            .copied()
+            .map(|(chunk_start, chunk_end)| {
+                scope.spawn(move || {
+                    let mut out = Vec::with_capacity((chunk_end - chunk_start) / 100);
+                    SinglePass::new(bytes, chunk_start, options)
+                        .records(chunk_end, &mut out)
+                        .map(|_| out)
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .map(|handle| handle.join().unwrap())
+            .collect()
+    });
+
+    let mut out = Vec::with_capacity((end - start) / 100);
+    for (result, (chunk_start, _)) in results.into_iter().zip(ranges) {
+        match result {
+            Ok(pairs) => out.extend(pairs),
+            // Every chunk before this one ended exactly on a record boundary, so this one starts at one.
+            // The records already in `out` keep the error's record index global
+            Err(_) => {
+                return SinglePass::new(bytes, chunk_start, options)
+                    .records(end, &mut out)
+                    .map(|_| out)
+            }
+        }
     }
     Ok(out)
 }
 
+impl TryFrom<&JsonValue> for CoordPair {
+    type Error = DeserializationError;
+
+    fn try_from(value: &JsonValue) -> Result<Self, Self::Error> {
+        pair_from_value(value, ParseOptions::default())
+    }
+}
+
+fn pair_from_value(
+    value: &JsonValue,
+    options: ParseOptions,
+) -> Result<CoordPair, DeserializationError> {
+    let Some(members) = value.as_object() else {
+        return Err(DeserializationError::new(
+            ErrorKind::UnexpectedByte(value.leading_byte()),
+            "Expected a pair object",
+        ));
+    };
+    let mut pair = PairMembers::default();
+    for (key, val) in members {
+        let Some(slot) = pair.slot(key.as_bytes(), options)? else {
+            continue;
+        };
+        *slot = Some(match val {
+            JsonValue::Number(val) => *val,
+            // Numbers quoted as strings are accepted as long as they parse
+            JsonValue::String(val) if !options.strict => {
+                parse_f64(val.trim().as_bytes()).ok_or_else(bad_number)?
+            }
+            _ => {
+                return Err(DeserializationError::new(
+                    ErrorKind::BadNumber,
+                    format!("member {key} is not a number"),
+                ))
+            }
+        });
+    }
+    pair.finish(missing_member)
+}
+
+/// Pairs from a parsed document: either a bare array, or an envelope object with a `pairs` array member
+pub fn pairs_from_value(value: &JsonValue) -> Result<Vec<CoordPair>, DeserializationError> {
+    pairs_from_value_with(value, ParseOptions::default())
+}
+
+pub fn pairs_from_value_with(
+    value: &JsonValue,
+    options: ParseOptions,
+) -> Result<Vec<CoordPair>, DeserializationError> {
+    let items = match value {
+        JsonValue::Array(items) => items,
+        JsonValue::Object(_) => value
+            .get("pairs")
+            .and_then(JsonValue::as_array)
+            .ok_or_else(|| {
+                DeserializationError::new(
+                    ErrorKind::MissingMember("pairs".to_string()),
+                    "Expected a pairs array",
+                )
+            })?,
+        _ => {
+            return Err(DeserializationError::new(
+                ErrorKind::UnexpectedByte(value.leading_byte()),
+                "Expected an array or an object",
+            ))
+        }
+    };
+    items
+        .iter()
+        .enumerate()
+        .map(|(i, item)| pair_from_value(item, options).map_err(|err| err.in_record(i)))
+        .collect()
+}
+
+/// Parses the whole document into a `JsonValue` first. Slower than the dedicated parsers, but accepts any layout
+pub fn deserialize_tree(input: &[u8]) -> Result<Vec<CoordPair>, DeserializationError> {
+    deserialize_tree_with(input, ParseOptions::default())
+}
+
+pub fn deserialize_tree_with(
+    input: &[u8],
+    options: ParseOptions,
+) -> Result<Vec<CoordPair>, DeserializationError> {
+    bench_block!(handle, "Parse Json Tree");
+    let value = parse_json(input)?;
+    record_bytes(input.len() as u64);
+    drop(handle);
+    bench_block!("Extract Pairs");
+    pairs_from_value_with(&value, options).map_err(|mut err| {
+        // The tree doesn't keep offsets, so find where the failing record starts
+        let offset = match err.record {
+            Some(record) => pair_offset(input, record).unwrap_or(0),
+            None => 0,
+        };
+        if let (ErrorKind::UnexpectedByte(b), Some(&actual)) = (&mut err.kind, input.get(offset)) {
+            *b = actual;
+        }
+        err.at(input, offset)
+    })
+}
+
 pub fn deserialize<D: Deserializable>(
     reader: &mut impl BufRead,
 ) -> Result<D, DeserializationError> {
     D::streaming_deserialize(reader)
 }
 
-pub fn serialize<S: Serializable>(
+pub fn serialize<S: Serializable + ?Sized>(
     obj: &mut S,
     writer: &mut impl Write,
 ) -> Result<(), std::io::Error> {
@@ -88,144 +620,386 @@ pub fn serialize<S: Serializable>(
     obj.streaming_serialize(writer)
 }
 
-impl<T> Deserializable for Vec<T>
-where
-    T: Deserializable,
-{
-    fn streaming_deserialize(reader: &mut impl BufRead) -> Result<Self, DeserializationError> {
-        let mut next_byte = [0u8; 1];
-        bench_block!(handle, "Deserialize Read");
-        reader.read_exact(&mut next_byte[..])?;
-        record_bytes(1);
-        drop(handle);
-        if next_byte[0] != b'[' {
-            return Err(DeserializationError(format!(
-                "Unexpected opening character '{}'",
-                next_byte[0] as char
-            )));
+/// Yields the elements of a JSON array as they're parsed, so memory stays flat however long the array is.
+/// Stops after the first error
+pub fn deserialize_iter<T: Deserializable>(
+    reader: impl BufRead,
+) -> impl Iterator<Item = Result<T, DeserializationError>> {
+    deserialize_iter_with(reader, ParseOptions::default())
+}
+
+pub fn deserialize_iter_with<T: Deserializable>(
+    reader: impl BufRead,
+    options: ParseOptions,
+) -> impl Iterator<Item = Result<T, DeserializationError>> {
+    ArrayIter::new(reader, options)
+}
+
+enum ArrayState {
+    Start,
+    Element,
+    Separator,
+    Done,
+}
+
+/// What `deserialize_iter` returns, for when the stats are wanted too, or recovery
+pub struct ArrayIter<R, T> {
+    reader: Tokenizer<R>,
+    state: ArrayState,
+    record: usize,
+    /// Only there in recovery mode
+    skipped: Option<Skipped>,
+    max_record_len: usize,
+    _element: PhantomData<T>,
+}
+
+/// How long a record recovery mode reads before giving up on finding its end, by default
+pub const MAX_RECORD_LEN: usize = 1 << 20;
+
+impl<R: BufRead, T: Deserializable> ArrayIter<R, T> {
+    pub fn new(reader: R, options: ParseOptions) -> Self {
+        Self {
+            reader: Tokenizer::new(reader).with_options(options),
+            state: ArrayState::Start,
+            record: 0,
+            skipped: None,
+            max_record_len: MAX_RECORD_LEN,
+            _element: PhantomData,
         }
-        let mut out = Vec::new();
+    }
 
-        'outer: loop {
-            out.push(T::streaming_deserialize(reader)?);
-            loop {
-                bench_block!(handle, "Deserialize Read");
-                reader.read_exact(&mut next_byte[..])?;
-                record_bytes(1);
-                drop(handle);
-
-                match next_byte[0] {
-                    b',' => break,
-                    b']' => break 'outer,
-                    x if x.is_ascii_whitespace() => {}
-                    x => {
-                        return Err(DeserializationError(format!(
-                            "Unexpected byte '{}'",
-                            x as char
-                        )))
+    /// Skips elements that don't parse instead of stopping at them, keeping the first `max_diagnostics` errors.
+    /// Every element is picked out before it's parsed, ending at the start of the next record if its own end
+    /// is missing, so the array can be picked up again after it. Running out of input and failing to read
+    /// still end the iteration
+    pub fn recovering(mut self, max_diagnostics: usize) -> Self {
+        self.skipped = Some(Skipped::new(max_diagnostics));
+        self
+    }
+
+    /// Caps how much of a record recovery mode holds on to, `MAX_RECORD_LEN` by default.
+    /// A longer one is skipped up to the start of the next
+    pub fn with_max_record_len(mut self, max_len: usize) -> Self {
+        self.max_record_len = max_len;
+        self
+    }
+
+    /// What's been read so far, the whole array once the iterator is done
+    pub fn stats(&self) -> ParseStats {
+        self.reader.stats()
+    }
+
+    /// What recovery mode skipped so far, `None` when not recovering
+    pub fn skipped(&self) -> Option<&Skipped> {
+        self.skipped.as_ref()
+    }
+
+    pub fn into_skipped(self) -> Option<Skipped> {
+        self.skipped
+    }
+
+    /// Parses the next element out of its span, which is consumed whether or not it parses
+    fn element_in_span(&mut self) -> (Result<T, DeserializationError>, RecordEnd) {
+        let options = self.reader.options();
+        let (element, end) = self.reader.with_record(self.max_record_len, |mut span| {
+            let mut reader = Tokenizer::new(&mut span).with_options(options);
+            let element = T::tokenized_deserialize(&mut reader)?;
+            if reader.skip_whitespace()?.is_some() {
+                return Err(reader.unexpected("Expected the end of the element"));
+            }
+            Ok((element, reader.stats()))
+        });
+        let element = element.map(|(element, nested)| {
+            self.reader.stats_mut().add_nested(nested);
+            element
+        });
+        (element, end)
+    }
+
+    /// Moves on to the next record after something broken, or to the end of the array
+    fn resync(&mut self) -> Result<(), DeserializationError> {
+        self.state = match self.reader.skip_to_record()? {
+            true => {
+                self.record += 1;
+                ArrayState::Element
+            }
+            false => ArrayState::Done,
+        };
+        Ok(())
+    }
+
+    fn truncated(&mut self) -> DeserializationError {
+        self.reader.locate(DeserializationError::new(
+            ErrorKind::TruncatedInput,
+            "Expected the rest of the array",
+        ))
+    }
+
+    fn step(&mut self) -> Result<Option<T>, DeserializationError> {
+        loop {
+            match self.state {
+                ArrayState::Start => {
+                    match self.reader.skip_whitespace()? {
+                        Some(b'[') => {}
+                        Some(first) => {
+                            let err = unexpected_at(&[first], 0, "Expected '['");
+                            return Err(self.reader.locate(err));
+                        }
+                        None => return Err(self.truncated()),
                     }
+                    self.reader.consume(1);
+                    self.state = match self.reader.skip_whitespace()? {
+                        Some(b']') => {
+                            self.reader.consume(1);
+                            ArrayState::Done
+                        }
+                        _ => ArrayState::Element,
+                    };
+                    record_bytes(self.reader.position().offset);
                 }
+                ArrayState::Element => {
+                    bench_block!("Deserialize Record");
+                    self.reader.skip_whitespace()?;
+                    let start = self.reader.position().offset;
+                    let record = self.record;
+                    let (element, end) = match self.skipped {
+                        None => (
+                            T::tokenized_deserialize(&mut self.reader),
+                            RecordEnd::Closed,
+                        ),
+                        Some(_) => self.element_in_span(),
+                    };
+                    let element = match (element, &mut self.skipped) {
+                        (Ok(element), _) => element,
+                        (Err(err), Some(skipped))
+                            if !matches!(
+                                err.kind,
+                                ErrorKind::Io(_) | ErrorKind::TruncatedInput
+                            ) =>
+                        {
+                            skipped.add(err.in_record(record));
+                            match end {
+                                RecordEnd::Closed | RecordEnd::Unopened => {
+                                    self.state = ArrayState::Separator
+                                }
+                                // Its `,` went with it
+                                RecordEnd::NextRecord => self.record += 1,
+                                RecordEnd::TooLong => self.resync()?,
+                            }
+                            continue;
+                        }
+                        (Err(err), _) => return Err(err.in_record(record)),
+                    };
+                    let read = self.reader.position().offset - start;
+                    self.reader.stats_mut().add_record(read);
+                    record_bytes(read);
+                    self.state = ArrayState::Separator;
+                    return Ok(Some(element));
+                }
+                ArrayState::Separator => {
+                    let before = self.reader.position().offset;
+                    let next_byte = match self.reader.skip_whitespace()? {
+                        Some(b) => b,
+                        None => return Err(self.truncated()),
+                    };
+                    if !matches!(next_byte, b',' | b']') {
+                        let err = unexpected_at(&[next_byte], 0, "Expected ',' or ']'");
+                        let err = self.reader.locate(err);
+                        let Some(skipped) = &mut self.skipped else {
+                            return Err(err.in_record(self.record));
+                        };
+                        // Whatever is in the way takes the place of a record
+                        self.record += 1;
+                        skipped.add(err.in_record(self.record));
+                        self.resync()?;
+                        continue;
+                    }
+                    self.reader.consume(1);
+                    record_bytes(self.reader.position().offset - before);
+                    self.record += 1;
+                    self.state = match next_byte {
+                        b',' => ArrayState::Element,
+                        _ => ArrayState::Done,
+                    };
+                }
+                ArrayState::Done => return Ok(None),
             }
         }
-        unsafe {
-            println!("{} {}", MIN_SIZE, MAX_SIZE);
-        }
+    }
+}
+
+impl<R: BufRead, T: Deserializable> Iterator for ArrayIter<R, T> {
+    type Item = Result<T, DeserializationError>;
 
-        Ok(out)
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.step() {
+            Ok(element) => element.map(Ok),
+            Err(err) => {
+                self.state = ArrayState::Done;
+                Some(Err(err))
+            }
+        }
     }
 }
 
-static mut MIN_SIZE: usize = usize::MAX;
-static mut MAX_SIZE: usize = 0;
+impl<T> Deserializable for Vec<T>
+where
+    T: Deserializable,
+{
+    fn streaming_deserialize(reader: &mut impl BufRead) -> Result<Self, DeserializationError> {
+        deserialize_iter(reader).collect()
+    }
+
+    /// Keeps the tokenizer's options for the elements, and adds to its stats
+    fn tokenized_deserialize<R: BufRead>(
+        reader: &mut Tokenizer<R>,
+    ) -> Result<Self, DeserializationError> {
+        let start = reader.position();
+        let options = reader.options();
+        let mut elements = ArrayIter::new(&mut *reader, options);
+        let out = (&mut elements).collect::<Result<Vec<_>, _>>();
+        let nested = elements.stats();
+        reader.stats_mut().add_nested(nested);
+        out.map_err(|err| err.after(start))
+    }
+}
 
 impl Deserializable for CoordPair {
     fn streaming_deserialize(reader: &mut impl BufRead) -> Result<Self, DeserializationError> {
-        let mut lat0: Option<f64> = None;
-        let mut lon0: Option<f64> = None;
-        let mut lat1: Option<f64> = None;
-        let mut lon1: Option<f64> = None;
-        let mut buf = vec![];
-        bench_block!(handle, "Deserialize Read");
-        let read = reader.read_until(b'}', &mut buf)?;
-        unsafe {
-            if read < MIN_SIZE {
-                MIN_SIZE = read;
-            }
-            if read > MAX_SIZE {
-                MAX_SIZE = read;
+        Self::tokenized_deserialize(&mut Tokenizer::new(reader))
+    }
+
+    /// Straight from the buffered window when it can be, see `parse_record`
+    fn tokenized_deserialize<R: BufRead>(
+        reader: &mut Tokenizer<R>,
+    ) -> Result<Self, DeserializationError> {
+        let options = reader.options();
+        match reader.with_buffered(|buf| parse_record(buf, options))? {
+            Some(record) => {
+                let stats = reader.stats_mut();
+                stats.whitespace_bytes += record.whitespace_bytes;
+                stats.ignored_keys += record.ignored_keys;
+                Ok(record.pair)
             }
+            None => read_members(reader),
         }
-        record_bytes(read as u64);
-        drop(handle);
-        if buf[0] != b'{' {
-            return Err(DeserializationError(format!(
-                "Unexpected opening character '{}'",
-                buf[0] as char
-            )));
-        }
-
-        let mut buf_slice = &buf[1..buf.len() - 1];
-        while !buf_slice.is_empty() {
-            bench_block!(handle, "Process Key Val Pair");
-            let next_colon = buf_slice.iter().position(|&b| b == b':').unwrap();
-            let key_slice = &buf_slice[0..next_colon];
-            buf_slice = &buf_slice[next_colon + 1..];
-            let next_comma = buf_slice
-                .iter()
-                .position(|&b| b == b',')
-                .unwrap_or(buf_slice.len());
-            let val_slice = &buf_slice[..next_comma];
-            drop(handle);
-            bench_block!(handle, "Process UTF8");
-            let key = unsafe { std::str::from_utf8_unchecked(key_slice).trim() };
-            let val_as_utf8 = unsafe { std::str::from_utf8_unchecked(val_slice).trim() };
-            drop(handle);
-
-            bench_block!(handle, "Parse float");
-            let val = val_as_utf8.parse::<f64>().map_err(|_| {
-                DeserializationError("Can't parse floating point value".to_string())
-            })?;
-            drop(handle);
-
-            if next_comma == buf_slice.len() {
-                buf_slice = &buf_slice[next_comma..];
-            } else {
-                buf_slice = &buf_slice[next_comma + 1..];
-            }
-            bench_block!(handle, "Match Key");
-            match key {
-                "\"lat0\"" => lat0 = Some(val),
-                "\"lon0\"" => lon0 = Some(val),
-                "\"lat1\"" => lat1 = Some(val),
-                "\"lon1\"" => lon1 = Some(val),
-                _ => {}
+    }
+}
+
+/// A pair `parse_record` read, and what it went through for the stats
+struct FlatRecord {
+    pair: CoordPair,
+    whitespace_bytes: u64,
+    ignored_keys: u64,
+}
+
+/// One `{...}` record of number members that's all in `buf`, read in place without going through the
+/// tokenizer. Returns how much of `buf` it took up. `None` for a record that doesn't end in `buf`, has
+/// a string or nested value, or anything wrong with it, all of which `read_members` then handles
+fn parse_record(buf: &[u8], options: ParseOptions) -> Option<(usize, FlatRecord)> {
+    let mut whitespace_bytes = 0;
+    let mut ignored_keys = 0;
+    let mut skip_whitespace = |pos: usize| {
+        let len = buf[pos..]
+            .iter()
+            .take_while(|b| b.is_ascii_whitespace())
+            .count();
+        whitespace_bytes += len as u64;
+        pos + len
+    };
+    let mut pos = skip_whitespace(0);
+    if buf.get(pos) != Some(&b'{') {
+        return None;
+    }
+    let mut members = PairMembers::default();
+    loop {
+        pos = skip_whitespace(pos + 1);
+        if buf.get(pos) != Some(&b'"') {
+            return None;
+        }
+        let key_start = pos + 1;
+        let key_len = buf[key_start..].iter().position(|&b| b == b'"')?;
+        let key = &buf[key_start..key_start + key_len];
+        if key.contains(&b'\\') {
+            return None;
+        }
+        pos = skip_whitespace(key_start + key_len + 1);
+        if buf.get(pos) != Some(&b':') {
+            return None;
+        }
+        pos = skip_whitespace(pos + 1);
+        let token_len = buf[pos..]
+            .iter()
+            .position(|&b| matches!(b, b',' | b'}' | b']' | b':') || b.is_ascii_whitespace())?;
+        let token = &buf[pos..pos + token_len];
+        if let [] | [b'"' | b'{' | b'[', ..] = token {
+            return None;
+        }
+        match members.slot(key, options).ok()? {
+            Some(slot) => *slot = Some(parse_number(token, options)?),
+            None => ignored_keys += 1,
+        }
+        pos = skip_whitespace(pos + token_len);
+        match buf.get(pos)? {
+            b',' => {}
+            b'}' => break,
+            _ => return None,
+        }
+    }
+    let pair = members.finish(missing_member).ok()?;
+    let record = FlatRecord {
+        pair,
+        whitespace_bytes,
+        ignored_keys,
+    };
+    Some((pos + 1, record))
+}
+
+/// A record a token at a time, for whatever `parse_record` leaves
+fn read_members<R: BufRead>(reader: &mut Tokenizer<R>) -> Result<CoordPair, DeserializationError> {
+    const KEYS: [&str; 4] = ["lat0", "lon0", "lat1", "lon1"];
+    let mut members = [None; 4];
+    reader.expect(b'{', "Expected '{'")?;
+    let mut first = true;
+    loop {
+        match reader.next_member(&KEYS.map(str::as_bytes), &mut first)? {
+            Member::End => break,
+            Member::Known(i) => {
+                if members[i].is_some() && reader.options().strict {
+                    return Err(reader.duplicate_member(KEYS[i]));
+                }
+                members[i] = Some(f64::tokenized_deserialize(reader)?);
             }
-            drop(handle);
+            Member::Unknown => reader.skip_value()?,
         }
-        let out = CoordPair {
-            lat0: lat0.ok_or_else(|| DeserializationError("member lat0 missing".to_owned()))?,
-            lon0: lon0.ok_or_else(|| DeserializationError("member lon0 missing".to_owned()))?,
-            lat1: lat1.ok_or_else(|| DeserializationError("member lat1 missing".to_owned()))?,
-            lon1: lon1.ok_or_else(|| DeserializationError("member lon1 missing".to_owned()))?,
+    }
+    let mut vals = [0.0; 4];
+    for (i, member) in members.into_iter().enumerate() {
+        vals[i] = match member {
+            Some(val) => val,
+            None => {
+                let err = DeserializationError::missing_member("CoordPair", KEYS[i]);
+                return Err(reader.locate(err));
+            }
         };
-        Ok(out)
     }
+    let [lat0, lon0, lat1, lon1] = vals;
+    Ok(CoordPair {
+        lat0,
+        lon0,
+        lat1,
+        lon1,
+    })
 }
 
-impl Serializable for CoordPair {
-    fn streaming_serialize(&mut self, writer: &mut impl Write) -> Result<(), std::io::Error> {
-        let mut buf = ryu::Buffer::new();
-        writer.write_all(b"{\"lat0\":")?;
-        writer.write_all(buf.format(self.lat0).as_bytes())?;
-        writer.write_all(b",\"lon0\":")?;
-        writer.write_all(buf.format(self.lon0).as_bytes())?;
-        writer.write_all(b",\"lon1\":")?;
-        writer.write_all(buf.format(self.lon1).as_bytes())?;
-        writer.write_all(b",\"lat1\":")?;
-        writer.write_all(buf.format(self.lat1).as_bytes())?;
-
-        writer.write_all(b"}")
-    }
+/// Reads a whole JSON array, and reports what it went through
+pub fn deserialize_with_stats<T: Deserializable>(
+    reader: impl BufRead,
+    options: ParseOptions,
+) -> Result<(Vec<T>, ParseStats), DeserializationError> {
+    let mut elements = ArrayIter::new(reader, options);
+    let out = (&mut elements).collect::<Result<Vec<_>, _>>()?;
+    Ok((out, elements.stats()))
 }
 
 pub trait Serializable {
@@ -234,17 +1008,354 @@ pub trait Serializable {
 
 impl<T: RngCore> Serializable for CoordPairGen<T> {
     fn streaming_serialize(&mut self, writer: &mut impl Write) -> Result<(), std::io::Error> {
-        writer.write_all(b"[")?;
+        write_json_array(self, writer)
+    }
+}
 
-        if let Some(mut item) = self.next() {
-            item.streaming_serialize(writer)?;
+pub(crate) fn write_json_array<T: Serializable>(
+    mut items: impl Iterator<Item = T>,
+    writer: &mut impl Write,
+) -> Result<(), std::io::Error> {
+    writer.write_all(b"[")?;
+
+    if let Some(mut item) = items.next() {
+        item.streaming_serialize(writer)?;
+    }
+    for mut item in items {
+        writer.write_all(b",")?;
+        item.streaming_serialize(writer)?;
+    }
+    writer.write_all(b"]")?;
+
+    Ok(())
+}
+
+/// On-disk layouts `hav` can write and read back
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    /// A single JSON array of pair objects
+    Json,
+    /// One pair object per line
+    Ndjson,
+    /// `lat0,lon0,lat1,lon1` header followed by one pair per row
+    Csv,
+    /// `BINARY_MAGIC` followed by four little-endian f64 per pair
+    Binary,
+    /// The haversine course's `{"pairs":[{"x0":..,"y0":..,"x1":..,"y1":..}]}`, x being the longitude
+    Reference,
+    /// `.hvb`, a header with the count and layout, f64 coordinates a pair at a time and a checksum
+    Hvb,
+    /// `.hvb` with the coordinates a column at a time, every lat0 first
+    HvbColumns,
+}
+
+const CSV_HEADER: &[u8] = b"lat0,lon0,lat1,lon1\n";
+
+// The last two bytes are the format version
+pub const BINARY_MAGIC: [u8; 8] = *b"HAVPR\0\0\x01";
+
+/// Whether the start of a file is an object with a `pairs` member first, a JSON envelope that
+/// `deserialize_tree` reads but the streaming parser, which wants a bare array, doesn't
+pub fn looks_like_envelope(head: &[u8]) -> bool {
+    let head = head.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(head);
+    head.trim_ascii_start()
+        .strip_prefix(b"{")
+        .is_some_and(|rest| rest.trim_ascii_start().starts_with(b"\"pairs\""))
+}
+
+impl Format {
+    pub fn write_pairs(
+        self,
+        pairs: impl Iterator<Item = CoordPair>,
+        writer: &mut impl Write,
+    ) -> Result<(), std::io::Error> {
+        bench_block!("Serialize Data");
+        match self {
+            Format::Json => write_json_array(pairs, writer),
+            Format::Ndjson => {
+                for mut item in pairs {
+                    item.streaming_serialize(writer)?;
+                    writer.write_all(b"\n")?;
+                }
+                Ok(())
+            }
+            Format::Csv => {
+                let mut buf = ryu::Buffer::new();
+                writer.write_all(CSV_HEADER)?;
+                for item in pairs {
+                    writer.write_all(buf.format(item.lat0).as_bytes())?;
+                    writer.write_all(b",")?;
+                    writer.write_all(buf.format(item.lon0).as_bytes())?;
+                    writer.write_all(b",")?;
+                    writer.write_all(buf.format(item.lat1).as_bytes())?;
+                    writer.write_all(b",")?;
+                    writer.write_all(buf.format(item.lon1).as_bytes())?;
+                    writer.write_all(b"\n")?;
+                }
+                Ok(())
+            }
+            Format::Binary => {
+                writer.write_all(&BINARY_MAGIC)?;
+                for item in pairs {
+                    for val in [item.lat0, item.lon0, item.lat1, item.lon1] {
+                        writer.write_all(&val.to_le_bytes())?;
+                    }
+                }
+                Ok(())
+            }
+            Format::Reference => write_reference(pairs, writer),
+            Format::Hvb => write_hvb(pairs, writer, Layout::Rows, Endian::NATIVE),
+            Format::HvbColumns => write_hvb(pairs, writer, Layout::Columns, Endian::NATIVE),
+        }
+    }
+
+    /// Guesses the format from the start of a file, `None` if it doesn't look like any of them
+    pub fn detect(head: &[u8]) -> Option<Format> {
+        if head.starts_with(&BINARY_MAGIC) {
+            return Some(Format::Binary);
         }
-        for mut item in self {
-            writer.write_all(b",")?;
-            item.streaming_serialize(writer)?;
+        // The layout doesn't matter for reading
+        if head.starts_with(&HVB_MAGIC) {
+            return Some(Format::Hvb);
         }
-        writer.write_all(b"]")?;
+        if looks_like_reference(head) {
+            return Some(Format::Reference);
+        }
+        let head = head
+            .strip_prefix(b"\xEF\xBB\xBF")
+            .unwrap_or(head)
+            .trim_ascii_start();
+        match head.first()? {
+            b'[' => Some(Format::Json),
+            // Not for the streaming parser, see `looks_like_envelope`
+            b'{' if looks_like_envelope(head) => Some(Format::Json),
+            b'{' => Some(Format::Ndjson),
+            _ => {
+                let header = head.split(|&b| b == b'\n').next()?;
+                let lat0 = header
+                    .split(|&b| b == b',')
+                    .any(|col| col.trim_ascii() == b"lat0");
+                lat0.then_some(Format::Csv)
+            }
+        }
+    }
 
-        Ok(())
+    pub fn read_pairs(
+        self,
+        reader: &mut impl BufRead,
+    ) -> Result<Vec<CoordPair>, DeserializationError> {
+        self.read_pairs_with(reader, ParseOptions::default())
     }
+
+    /// `options` only matter to the JSON formats
+    pub fn read_pairs_with(
+        self,
+        reader: &mut impl BufRead,
+        options: ParseOptions,
+    ) -> Result<Vec<CoordPair>, DeserializationError> {
+        match self {
+            Format::Json => deserialize_iter_with(reader, options).collect(),
+            Format::Ndjson => deserialize_ndjson_with(reader, options),
+            Format::Csv => deserialize_csv(reader),
+            Format::Binary => deserialize_binary(reader),
+            Format::Reference => deserialize_reference(reader, options),
+            Format::Hvb | Format::HvbColumns => {
+                let mut input = Vec::new();
+                reader.read_to_end(&mut input)?;
+                deserialize_hvb(&input)
+            }
+        }
+    }
+}
+
+pub fn deserialize_ndjson(
+    reader: &mut impl BufRead,
+) -> Result<Vec<CoordPair>, DeserializationError> {
+    deserialize_ndjson_with(reader, ParseOptions::default())
+}
+
+pub fn deserialize_ndjson_with(
+    reader: &mut impl BufRead,
+    options: ParseOptions,
+) -> Result<Vec<CoordPair>, DeserializationError> {
+    read_ndjson(reader, options, None)
+}
+
+/// Skips lines that don't parse instead of stopping at them, adding them to `skipped`
+pub fn deserialize_ndjson_recovering(
+    reader: &mut impl BufRead,
+    options: ParseOptions,
+    skipped: &mut Skipped,
+) -> Result<Vec<CoordPair>, DeserializationError> {
+    read_ndjson(reader, options, Some(skipped))
+}
+
+fn read_ndjson(
+    reader: &mut impl BufRead,
+    options: ParseOptions,
+    mut skipped: Option<&mut Skipped>,
+) -> Result<Vec<CoordPair>, DeserializationError> {
+    let mut out = Vec::new();
+    let mut line = Vec::new();
+    let mut position = Position::default();
+    let mut record = 0;
+    loop {
+        line.clear();
+        bench_block!(handle, "Deserialize Read");
+        let read = reader.read_until(b'\n', &mut line)?;
+        record_bytes(read as u64);
+        drop(handle);
+        if read == 0 {
+            break;
+        }
+        let line_start = position;
+        position.advance(&line);
+        let trimmed = line.trim_ascii();
+        if trimmed.is_empty() {
+            continue;
+        }
+        let mut pair_start = line_start;
+        pair_start.advance(&line[..offset_in(&line, trimmed)]);
+        let mut rest = trimmed;
+        let pair =
+            CoordPair::tokenized_deserialize(&mut Tokenizer::new(&mut rest).with_options(options))
+                .map_err(|err| err.after(pair_start))
+                .and_then(|pair| match rest {
+                    [] => Ok(pair),
+                    _ => Err(unexpected_at(
+                        &line,
+                        offset_in(&line, rest),
+                        "Expected the end of the line",
+                    )
+                    .after(line_start)),
+                })
+                .map_err(|err| err.in_record(record));
+        record += 1;
+        match (pair, skipped.as_deref_mut()) {
+            (Ok(pair), _) => out.push(pair),
+            // Every line is a record, so there's nothing to resynchronize
+            (Err(err), Some(skipped)) => skipped.add(err),
+            (Err(err), _) => return Err(err),
+        }
+    }
+    Ok(out)
+}
+
+pub fn deserialize_csv(reader: &mut impl BufRead) -> Result<Vec<CoordPair>, DeserializationError> {
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    let mut position = Position::default();
+    position.advance(line.as_bytes());
+    let header: Vec<&str> = line.trim().split(',').map(str::trim).collect();
+    let column = |name: &str| {
+        header.iter().position(|&col| col == name).ok_or_else(|| {
+            DeserializationError::new(
+                ErrorKind::MissingMember(name.to_string()),
+                "The header needs lat0, lon0, lat1 and lon1 columns",
+            )
+            .at(line.as_bytes(), 0)
+        })
+    };
+    let columns = [
+        column("lat0")?,
+        column("lon0")?,
+        column("lat1")?,
+        column("lon1")?,
+    ];
+    let width = header.len();
+
+    let mut out = Vec::new();
+    let mut row = Vec::with_capacity(width);
+    loop {
+        line.clear();
+        bench_block!(handle, "Deserialize Read");
+        let read = reader.read_line(&mut line)?;
+        record_bytes(read as u64);
+        drop(handle);
+        if read == 0 {
+            break;
+        }
+        let line_start = position;
+        position.advance(line.as_bytes());
+        if line.trim().is_empty() {
+            continue;
+        }
+        let bytes = line.as_bytes();
+        let record = out.len();
+        row.clear();
+        for field in line.trim().split(',') {
+            row.push(parse_f64(field.trim().as_bytes()).ok_or_else(|| {
+                bad_number()
+                    .at(bytes, offset_in(bytes, field.as_bytes()))
+                    .after(line_start)
+                    .in_record(record)
+            })?);
+        }
+        if row.len() != width {
+            let kind = if row.len() < width {
+                ErrorKind::TruncatedInput
+            } else {
+                ErrorKind::UnexpectedByte(b',')
+            };
+            return Err(DeserializationError::new(
+                kind,
+                format!("Expected {} columns, found {}", width, row.len()),
+            )
+            .at(bytes, 0)
+            .after(line_start)
+            .in_record(record));
+        }
+        out.push(CoordPair {
+            lat0: row[columns[0]],
+            lon0: row[columns[1]],
+            lat1: row[columns[2]],
+            lon1: row[columns[3]],
+        });
+    }
+    Ok(out)
+}
+
+pub fn deserialize_binary(
+    reader: &mut impl BufRead,
+) -> Result<Vec<CoordPair>, DeserializationError> {
+    let mut magic = [0u8; 8];
+    reader.read_exact(&mut magic)?;
+    if magic != BINARY_MAGIC {
+        let offset = magic
+            .iter()
+            .zip(BINARY_MAGIC)
+            .position(|(&a, b)| a != b)
+            .unwrap_or(0);
+        return Err(unexpected_at(&magic, offset, "Unexpected binary header"));
+    }
+    let mut out = Vec::new();
+    let mut record = [0u8; 32];
+    loop {
+        bench_block!(handle, "Deserialize Read");
+        if reader.fill_buf()?.is_empty() {
+            break;
+        }
+        reader.read_exact(&mut record).map_err(|err| {
+            // Lines and columns mean nothing here, only the offset is set
+            let position = Position {
+                offset: (magic.len() + out.len() * record.len()) as u64,
+                ..Position::default()
+            };
+            DeserializationError::from(err)
+                .after(position)
+                .in_record(out.len())
+        })?;
+        record_bytes(record.len() as u64);
+        drop(handle);
+        let mut vals = record
+            .chunks_exact(8)
+            .map(|bytes| f64::from_le_bytes(bytes.try_into().unwrap()));
+        out.push(CoordPair {
+            lat0: vals.next().unwrap(),
+            lon0: vals.next().unwrap(),
+            lat1: vals.next().unwrap(),
+            lon1: vals.next().unwrap(),
+        });
+    }
+    Ok(out)
 }
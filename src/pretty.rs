@@ -0,0 +1,153 @@
+use std::io::{self, Write};
+
+use crate::parser::Serializable;
+
+// Indents the compact JSON the serializers write as it passes through, so every `Serializable` can be
+// pretty printed without knowing about it. Whitespace outside of strings is dropped and put back by the rules
+// in `PrettyConfig`
+
+/// How `PrettyWriter` lays out the JSON
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PrettyConfig {
+    /// Written once per level of nesting at the start of each line
+    pub indent: String,
+    pub newline: String,
+    /// A space after every `:`, and after every `,` in containers kept on one line
+    pub spaces: bool,
+    /// Containers nested deeper than this are kept on one line. With 1, the elements of a top level
+    /// array get a line each
+    pub expand_depth: usize,
+}
+
+impl Default for PrettyConfig {
+    fn default() -> Self {
+        Self {
+            indent: "  ".to_string(),
+            newline: "\n".to_string(),
+            spaces: true,
+            expand_depth: usize::MAX,
+        }
+    }
+}
+
+/// Writes `obj` through a `PrettyWriter`
+pub fn serialize_pretty<S: Serializable + ?Sized>(
+    obj: &mut S,
+    writer: &mut impl Write,
+    config: PrettyConfig,
+) -> Result<(), io::Error> {
+    let mut writer = PrettyWriter::new(writer, config);
+    obj.streaming_serialize(&mut writer)
+}
+
+pub struct PrettyWriter<W> {
+    inner: W,
+    config: PrettyConfig,
+    depth: usize,
+    in_string: bool,
+    escaped: bool,
+    /// A container was just opened, and whether it gets a line break depends on whether it's empty
+    opened: bool,
+    out: Vec<u8>,
+}
+
+impl<W: Write> PrettyWriter<W> {
+    pub fn new(inner: W, config: PrettyConfig) -> Self {
+        Self {
+            inner,
+            config,
+            depth: 0,
+            in_string: false,
+            escaped: false,
+            opened: false,
+            out: Vec::new(),
+        }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+
+    /// Whether the containers at the current depth get a line per element
+    fn expanded(&self) -> bool {
+        self.depth <= self.config.expand_depth
+    }
+
+    fn line(&mut self, depth: usize) {
+        self.out.extend_from_slice(self.config.newline.as_bytes());
+        for _ in 0..depth {
+            self.out.extend_from_slice(self.config.indent.as_bytes());
+        }
+    }
+
+    /// Breaks the line after an opening bracket once it's clear the container isn't empty
+    fn open_pending(&mut self) {
+        if std::mem::take(&mut self.opened) && self.expanded() {
+            self.line(self.depth);
+        }
+    }
+
+    fn byte(&mut self, b: u8) {
+        if self.in_string {
+            match b {
+                _ if self.escaped => self.escaped = false,
+                b'\\' => self.escaped = true,
+                b'"' => self.in_string = false,
+                _ => {}
+            }
+            self.out.push(b);
+            return;
+        }
+        match b {
+            b' ' | b'\n' | b'\r' | b'\t' => {}
+            b'{' | b'[' => {
+                self.open_pending();
+                self.out.push(b);
+                self.depth += 1;
+                self.opened = true;
+            }
+            b'}' | b']' => {
+                if !std::mem::take(&mut self.opened) && self.expanded() {
+                    // A stray closing bracket at the top level stays on the line
+                    self.line(self.depth.saturating_sub(1));
+                }
+                self.out.push(b);
+                self.depth = self.depth.saturating_sub(1);
+            }
+            b',' => {
+                self.out.push(b);
+                if self.expanded() {
+                    self.line(self.depth);
+                } else if self.config.spaces {
+                    self.out.push(b' ');
+                }
+            }
+            b':' => {
+                self.out.push(b);
+                if self.config.spaces {
+                    self.out.push(b' ');
+                }
+            }
+            _ => {
+                self.open_pending();
+                self.in_string = b == b'"';
+                self.out.push(b);
+            }
+        }
+    }
+}
+
+impl<W: Write> Write for PrettyWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.out.clear();
+        for &b in buf {
+            self.byte(b);
+        }
+        self.inner.write_all(&self.out)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
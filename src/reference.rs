@@ -0,0 +1,239 @@
+use std::io::{self, BufRead, Read, Write};
+
+use crate::{
+    parser::{Deserializable, DeserializationError, ParseOptions, Serializable},
+    tokenizer::Tokenizer,
+    CoordPair,
+};
+
+// The haversine course's reference files, so results can be checked against the reference implementation.
+// Inputs are `{"pairs":[{"x0":..,"y0":..,"x1":..,"y1":..}, ...]}` with x the longitude and y the latitude.
+// Each comes with a `.f64` answer file of little-endian f64s: every pair's distance, then their average
+
+/// A pair the way the reference files spell it
+#[derive(Serializable, Deserializable)]
+struct ReferencePair {
+    x0: f64,
+    y0: f64,
+    x1: f64,
+    y1: f64,
+}
+
+impl From<CoordPair> for ReferencePair {
+    fn from(pair: CoordPair) -> Self {
+        Self {
+            x0: pair.lon0,
+            y0: pair.lat0,
+            x1: pair.lon1,
+            y1: pair.lat1,
+        }
+    }
+}
+
+impl From<ReferencePair> for CoordPair {
+    fn from(pair: ReferencePair) -> Self {
+        Self {
+            lat0: pair.y0,
+            lon0: pair.x0,
+            lat1: pair.y1,
+            lon1: pair.x1,
+        }
+    }
+}
+
+#[derive(Deserializable)]
+struct ReferenceFile {
+    pairs: Vec<ReferencePair>,
+}
+
+pub fn deserialize_reference(
+    reader: &mut impl BufRead,
+    options: ParseOptions,
+) -> Result<Vec<CoordPair>, DeserializationError> {
+    let file =
+        ReferenceFile::tokenized_deserialize(&mut Tokenizer::new(reader).with_options(options))?;
+    Ok(file.pairs.into_iter().map(CoordPair::from).collect())
+}
+
+pub fn write_reference(
+    pairs: impl Iterator<Item = CoordPair>,
+    writer: &mut impl Write,
+) -> Result<(), io::Error> {
+    writer.write_all(b"{\"pairs\":[")?;
+    for (i, pair) in pairs.enumerate() {
+        if i != 0 {
+            writer.write_all(b",")?;
+        }
+        ReferencePair::from(pair).streaming_serialize(writer)?;
+    }
+    writer.write_all(b"]}")
+}
+
+/// Whether the start of a file, as much of it as there is, is a reference input: an object with a `pairs`
+/// member first, whose first pair has an x or y member
+pub fn looks_like_reference(head: &[u8]) -> bool {
+    let head = head.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(head);
+    let Some(rest) = head.trim_ascii_start().strip_prefix(b"{") else {
+        return false;
+    };
+    let Some(rest) = rest.trim_ascii_start().strip_prefix(b"\"pairs\"") else {
+        return false;
+    };
+    let first_pair = rest.split(|&b| b == b'}').next().unwrap_or(rest);
+    [&b"\"x0\""[..], b"\"y0\"", b"\"x1\"", b"\"y1\""]
+        .iter()
+        .any(|key| first_pair.windows(key.len()).any(|window| window == *key))
+}
+
+// Distances are computed the same way, but the libm calls can be an ulp or so apart between implementations
+const ANSWER_TOLERANCE: f64 = 1e-9;
+
+fn close(a: f64, b: f64) -> bool {
+    (a - b).abs() <= ANSWER_TOLERANCE * a.abs().max(b.abs()).max(1.0)
+}
+
+/// The contents of a `.f64` answer file
+#[derive(Debug, Clone, PartialEq)]
+pub struct Answers {
+    pub distances: Vec<f64>,
+    pub average: f64,
+}
+
+/// How computed results compare to an answer file
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AnswerCheck {
+    pub checked: usize,
+    /// How many of the pairs' distances are off
+    pub mismatches: usize,
+    /// The first pair that's off: its index, the computed distance and the answer
+    pub first_mismatch: Option<(usize, f64, f64)>,
+    pub largest_difference: f64,
+    /// The answer file's average
+    pub average: f64,
+    pub average_matches: bool,
+}
+
+impl AnswerCheck {
+    pub fn passed(&self) -> bool {
+        self.mismatches == 0 && self.average_matches
+    }
+}
+
+/// The next f64 of an answer file, `None` at its end
+fn read_answer(reader: &mut impl Read) -> Result<Option<f64>, io::Error> {
+    let mut bytes = [0; 8];
+    let mut filled = 0;
+    while filled < bytes.len() {
+        match reader.read(&mut bytes[filled..]) {
+            Ok(0) => break,
+            Ok(read) => filled += read,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    match filled {
+        0 => Ok(None),
+        8 => Ok(Some(f64::from_le_bytes(bytes))),
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "answer files hold whole f64s, this one ends part way through one",
+        )),
+    }
+}
+
+fn no_average() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        "answer files hold at least the average, this one is empty",
+    )
+}
+
+impl Answers {
+    pub fn read(reader: &mut impl Read) -> Result<Self, io::Error> {
+        let mut distances = vec![];
+        while let Some(val) = read_answer(reader)? {
+            distances.push(val);
+        }
+        let average = distances.pop().ok_or_else(no_average)?;
+        Ok(Self { distances, average })
+    }
+
+    pub fn write(&self, writer: &mut impl Write) -> Result<(), io::Error> {
+        for val in self.distances.iter().chain([&self.average]) {
+            writer.write_all(&val.to_le_bytes())?;
+        }
+        Ok(())
+    }
+}
+
+/// Checks distances against an answer file as they're computed, reading it alongside them rather than
+/// keeping either in memory. Matches are within a relative tolerance of 1e-9
+pub struct AnswerChecker<R> {
+    reader: R,
+    /// Read one ahead, the last value in the file being the average rather than a distance
+    next: f64,
+    /// Set once `next` is known to be the average
+    at_average: bool,
+    distances: usize,
+    check: AnswerCheck,
+}
+
+impl<R: Read> AnswerChecker<R> {
+    pub fn new(mut reader: R) -> Result<Self, io::Error> {
+        let next = read_answer(&mut reader)?.ok_or_else(no_average)?;
+        Ok(Self {
+            reader,
+            next,
+            at_average: false,
+            distances: 0,
+            check: AnswerCheck::default(),
+        })
+    }
+
+    pub fn add(&mut self, distance: f64) -> Result<(), io::Error> {
+        let index = self.check.checked;
+        self.check.checked += 1;
+        if self.at_average {
+            return Ok(());
+        }
+        let Some(following) = read_answer(&mut self.reader)? else {
+            // More pairs than answers, `finish` reports it
+            self.at_average = true;
+            return Ok(());
+        };
+        let answer = std::mem::replace(&mut self.next, following);
+        self.distances += 1;
+        let check = &mut self.check;
+        check.largest_difference = check.largest_difference.max((distance - answer).abs());
+        if !close(distance, answer) {
+            check.mismatches += 1;
+            check
+                .first_mismatch
+                .get_or_insert((index, distance, answer));
+        }
+        Ok(())
+    }
+
+    /// Compares `average` and reads the rest of the file.
+    /// A different number of pairs than distances is an error rather than a failed check
+    pub fn finish(mut self, average: f64) -> Result<AnswerCheck, io::Error> {
+        if !self.at_average {
+            while let Some(following) = read_answer(&mut self.reader)? {
+                self.next = following;
+                self.distances += 1;
+            }
+        }
+        if self.distances != self.check.checked {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "the answers have {} distances but the input has {} pairs",
+                    self.distances, self.check.checked
+                ),
+            ));
+        }
+        self.check.average = self.next;
+        self.check.average_matches = close(average, self.next);
+        Ok(self.check)
+    }
+}
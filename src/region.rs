@@ -0,0 +1,282 @@
+use std::{fmt::Display, str::FromStr};
+
+use rand::RngCore;
+
+use crate::rng::{gen_range, gen_unit};
+
+// Regions generation can be restricted to. Sampling is uniform over the sphere's surface, not over lat/lon,
+// so a box near the pole doesn't get denser than one at the equator
+
+// Give up on rejection sampling a sub-box after this many misses and sample the whole region instead
+const MAX_REJECTIONS: usize = 1000;
+
+/// Maps any longitude into [-180, 180)
+pub fn wrap_lon(lon: f64) -> f64 {
+    (lon + 180.0).rem_euclid(360.0) - 180.0
+}
+
+fn parse_lat_lon(s: &str) -> Result<(f64, f64), String> {
+    let (lat, lon) = s
+        .split_once(',')
+        .ok_or_else(|| format!("expected 'lat,lon', found '{s}'"))?;
+    let lat: f64 = lat
+        .trim()
+        .parse()
+        .map_err(|_| format!("invalid latitude '{lat}'"))?;
+    let lon: f64 = lon
+        .trim()
+        .parse()
+        .map_err(|_| format!("invalid longitude '{lon}'"))?;
+    if !(-90.0..=90.0).contains(&lat) || !(-180.0..=180.0).contains(&lon) {
+        return Err(format!("'{s}' is outside the valid lat/lon range"));
+    }
+    Ok((lat, lon))
+}
+
+/// Lat/lon box. A box with `min_lon > max_lon` crosses the antimeridian
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoundingBox {
+    pub min_lat: f64,
+    pub max_lat: f64,
+    pub min_lon: f64,
+    pub max_lon: f64,
+}
+
+impl BoundingBox {
+    pub const GLOBE: BoundingBox = BoundingBox {
+        min_lat: -90.0,
+        max_lat: 90.0,
+        min_lon: -180.0,
+        max_lon: 180.0,
+    };
+
+    pub fn lon_span(&self) -> f64 {
+        if self.min_lon <= self.max_lon {
+            self.max_lon - self.min_lon
+        } else {
+            self.max_lon - self.min_lon + 360.0
+        }
+    }
+
+    pub fn contains(&self, lat: f64, lon: f64) -> bool {
+        (self.min_lat..=self.max_lat).contains(&lat)
+            && (lon - self.min_lon).rem_euclid(360.0) <= self.lon_span()
+    }
+
+    /// Grows the box just enough to take in a point. Longitudes are compared unwrapped around `center_lon`,
+    /// which has to be inside the box, so a box grown past the antimeridian wraps instead of spanning the globe
+    pub fn grow_to(&mut self, lat: f64, lon: f64, center_lon: f64) {
+        self.min_lat = self.min_lat.min(lat);
+        self.max_lat = self.max_lat.max(lat);
+        if self.lon_span() >= 360.0 {
+            return;
+        }
+        let offset = wrap_lon(lon - center_lon);
+        let west = (center_lon - self.min_lon).rem_euclid(360.0).max(-offset);
+        let east = (self.max_lon - center_lon).rem_euclid(360.0).max(offset);
+        if west + east >= 360.0 {
+            (self.min_lon, self.max_lon) = (-180.0, 180.0);
+        } else {
+            self.min_lon = wrap_lon(center_lon - west);
+            self.max_lon = wrap_lon(center_lon + east);
+        }
+    }
+
+    pub fn sample(&self, rng: &mut impl RngCore) -> (f64, f64) {
+        let z = gen_range(
+            rng,
+            self.min_lat.to_radians().sin(),
+            self.max_lat.to_radians().sin(),
+        );
+        let lat = z.asin().to_degrees().clamp(self.min_lat, self.max_lat);
+        let lon = wrap_lon(self.min_lon + self.lon_span() * gen_unit(rng));
+        (lat, lon)
+    }
+}
+
+/// `min_lat,min_lon,max_lat,max_lon`
+impl FromStr for BoundingBox {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<&str> = s.split(',').collect();
+        let [min_lat, min_lon, max_lat, max_lon] = parts[..] else {
+            return Err("expected 'min_lat,min_lon,max_lat,max_lon'".to_string());
+        };
+        let (min_lat, min_lon) = parse_lat_lon(&format!("{min_lat},{min_lon}"))?;
+        let (max_lat, max_lon) = parse_lat_lon(&format!("{max_lat},{max_lon}"))?;
+        if min_lat >= max_lat {
+            return Err("min_lat must be smaller than max_lat".to_string());
+        }
+        if min_lon == max_lon {
+            return Err("longitude range is empty".to_string());
+        }
+        Ok(Self {
+            min_lat,
+            max_lat,
+            min_lon,
+            max_lon,
+        })
+    }
+}
+
+impl Display for BoundingBox {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{},{},{},{}",
+            self.min_lat, self.min_lon, self.max_lat, self.max_lon
+        )
+    }
+}
+
+/// Simple polygon with straight edges in lat/lon space. Longitudes are stored unwrapped,
+/// so a polygon crossing the antimeridian is contiguous rather than split in two
+#[derive(Debug, Clone, PartialEq)]
+pub struct Polygon {
+    vertices: Vec<(f64, f64)>,
+    min_lon: f64,
+    max_lon: f64,
+    bounds: BoundingBox,
+}
+
+impl Polygon {
+    pub fn new(vertices: &[(f64, f64)]) -> Result<Self, String> {
+        if vertices.len() < 3 {
+            return Err("a polygon needs at least 3 vertices".to_string());
+        }
+        let mut unwrapped = Vec::with_capacity(vertices.len());
+        let mut prev_lon = vertices[0].1;
+        for &(lat, lon) in vertices {
+            let lon = prev_lon + wrap_lon(lon - prev_lon);
+            unwrapped.push((lat, lon));
+            prev_lon = lon;
+        }
+        let closing = unwrapped[0].1 - prev_lon;
+        if wrap_lon(closing) != closing {
+            return Err("polygon winds around a pole, which isn't supported".to_string());
+        }
+        let min_lat = unwrapped.iter().map(|v| v.0).fold(f64::INFINITY, f64::min);
+        let max_lat = unwrapped
+            .iter()
+            .map(|v| v.0)
+            .fold(f64::NEG_INFINITY, f64::max);
+        let min_lon = unwrapped.iter().map(|v| v.1).fold(f64::INFINITY, f64::min);
+        let max_lon = unwrapped
+            .iter()
+            .map(|v| v.1)
+            .fold(f64::NEG_INFINITY, f64::max);
+        if min_lat == max_lat || min_lon == max_lon {
+            return Err("polygon has no area".to_string());
+        }
+        Ok(Self {
+            vertices: unwrapped,
+            min_lon,
+            max_lon,
+            bounds: BoundingBox {
+                min_lat,
+                max_lat,
+                min_lon: wrap_lon(min_lon),
+                max_lon: wrap_lon(max_lon),
+            },
+        })
+    }
+
+    pub fn contains(&self, lat: f64, lon: f64) -> bool {
+        // Move the point into the same unwrapped range as the vertices
+        let lon = self.min_lon + (lon - self.min_lon).rem_euclid(360.0);
+        if lon > self.max_lon {
+            return false;
+        }
+        // Even-odd ray casting along the latitude axis
+        let mut inside = false;
+        let mut j = self.vertices.len() - 1;
+        for (i, &(lat_i, lon_i)) in self.vertices.iter().enumerate() {
+            let (lat_j, lon_j) = self.vertices[j];
+            if (lon_i > lon) != (lon_j > lon)
+                && lat < (lat_j - lat_i) * (lon - lon_i) / (lon_j - lon_i) + lat_i
+            {
+                inside = !inside;
+            }
+            j = i;
+        }
+        inside
+    }
+}
+
+/// `lat,lon;lat,lon;...`, at least three vertices
+impl FromStr for Polygon {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let vertices = s
+            .split(';')
+            .filter(|v| !v.trim().is_empty())
+            .map(parse_lat_lon)
+            .collect::<Result<Vec<_>, _>>()?;
+        Polygon::new(&vertices)
+    }
+}
+
+impl Display for Polygon {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (i, (lat, lon)) in self.vertices.iter().enumerate() {
+            if i != 0 {
+                write!(f, ";")?;
+            }
+            write!(f, "{},{}", lat, wrap_lon(*lon))?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Region {
+    BoundingBox(BoundingBox),
+    Polygon(Polygon),
+}
+
+impl Region {
+    pub fn bounds(&self) -> BoundingBox {
+        match self {
+            Region::BoundingBox(bbox) => *bbox,
+            Region::Polygon(polygon) => polygon.bounds,
+        }
+    }
+
+    pub fn contains(&self, lat: f64, lon: f64) -> bool {
+        match self {
+            Region::BoundingBox(bbox) => bbox.contains(lat, lon),
+            Region::Polygon(polygon) => polygon.contains(lat, lon),
+        }
+    }
+
+    pub fn sample(&self, rng: &mut impl RngCore) -> (f64, f64) {
+        self.sample_within(&self.bounds(), rng)
+    }
+
+    /// Uniform point in the intersection of `bbox` and the region, by rejection from `bbox`
+    pub fn sample_within(&self, bbox: &BoundingBox, rng: &mut impl RngCore) -> (f64, f64) {
+        self.sample_by(rng, |rng| bbox.sample(rng))
+    }
+
+    /// Draws from `propose` until a point lands inside the region
+    pub fn sample_by<R: RngCore>(
+        &self,
+        rng: &mut R,
+        mut propose: impl FnMut(&mut R) -> (f64, f64),
+    ) -> (f64, f64) {
+        for _ in 0..MAX_REJECTIONS {
+            let (lat, lon) = propose(rng);
+            if self.contains(lat, lon) {
+                return (lat, lon);
+            }
+        }
+        loop {
+            let (lat, lon) = self.bounds().sample(rng);
+            if self.contains(lat, lon) {
+                return (lat, lon);
+            }
+        }
+    }
+}
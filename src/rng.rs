@@ -0,0 +1,97 @@
+use rand::{Error, RngCore, SeedableRng};
+
+// xoshiro256** (Blackman & Vigna), seeded through splitmix64
+// Implemented here rather than pulled from rand because StdRng is allowed to change algorithms between releases,
+// and a given seed has to keep producing byte-identical input files
+#[derive(Debug, Clone)]
+pub struct Xoshiro256StarStar {
+    s: [u64; 4],
+}
+
+fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+impl RngCore for Xoshiro256StarStar {
+    fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let result = self.s[1].wrapping_mul(5).rotate_left(7).wrapping_mul(9);
+        let t = self.s[1] << 17;
+        self.s[2] ^= self.s[0];
+        self.s[3] ^= self.s[1];
+        self.s[1] ^= self.s[2];
+        self.s[0] ^= self.s[3];
+        self.s[2] ^= t;
+        self.s[3] = self.s[3].rotate_left(45);
+        result
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(8) {
+            let bytes = self.next_u64().to_le_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+impl SeedableRng for Xoshiro256StarStar {
+    type Seed = [u8; 32];
+
+    fn from_seed(seed: Self::Seed) -> Self {
+        let mut s = [0u64; 4];
+        for (word, bytes) in s.iter_mut().zip(seed.chunks_exact(8)) {
+            *word = u64::from_le_bytes(bytes.try_into().unwrap());
+        }
+        // The all-zero state is a fixed point of the generator
+        if s == [0; 4] {
+            return Self::seed_from_u64(0);
+        }
+        Self { s }
+    }
+
+    fn seed_from_u64(mut state: u64) -> Self {
+        Self {
+            s: [
+                splitmix64(&mut state),
+                splitmix64(&mut state),
+                splitmix64(&mut state),
+                splitmix64(&mut state),
+            ],
+        }
+    }
+}
+
+/// Uniform f64 in [0, 1) from the top 53 bits of the next output
+pub fn gen_unit(rng: &mut impl RngCore) -> f64 {
+    (rng.next_u64() >> 11) as f64 * (1.0 / (1u64 << 53) as f64)
+}
+
+/// Uniform f64 in [min, max). Unlike `rand::Rng::gen_range` the mapping is fixed here, and an empty range yields `min`
+pub fn gen_range(rng: &mut impl RngCore, min: f64, max: f64) -> f64 {
+    min + (max - min) * gen_unit(rng)
+}
+
+/// Index in [0, len), via the float mapping so it stays fixed along with `gen_range`
+pub fn gen_index(rng: &mut impl RngCore, len: usize) -> usize {
+    ((gen_unit(rng) * len as f64) as usize).min(len - 1)
+}
+
+/// Standard normal sample via Box-Muller
+pub fn gen_normal(rng: &mut impl RngCore) -> f64 {
+    // 1 - u keeps the log argument in (0, 1]
+    let u1 = 1.0 - gen_unit(rng);
+    let u2 = gen_unit(rng);
+    (-2.0 * u1.ln()).sqrt() * (std::f64::consts::TAU * u2).cos()
+}
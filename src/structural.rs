@@ -0,0 +1,386 @@
+use crate::{
+    bench_block,
+    metrics::record_bytes,
+    parser::{
+        bad_number, missing_member, parse_number, unexpected_at, DeserializationError, ErrorKind,
+        PairMembers, ParseOptions,
+    },
+    CoordPair,
+};
+
+// Two stage parser in the style of simdjson. Stage one classifies the input 64 bytes at a time with SIMD
+// and records every structural character outside of strings in a bitmap, along with the quotes themselves.
+// Stage two walks the set bits, so it only ever looks at the bytes between two structurals to read a number
+
+const BLOCK: usize = 64;
+const EVEN_BITS: u64 = 0x5555_5555_5555_5555;
+
+/// Per byte masks of one block, bit i for byte i
+#[derive(Default)]
+struct Masks {
+    quote: u64,
+    backslash: u64,
+    /// `{`, `}`, `[`, `]`, `:` and `,`
+    op: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StructuralIndex {
+    words: Vec<u64>,
+}
+
+impl StructuralIndex {
+    #[cfg(target_arch = "x86_64")]
+    pub fn new(input: &[u8]) -> Self {
+        if is_x86_feature_detected!("avx2") {
+            unsafe { build_avx2(input) }
+        } else {
+            // SSE2 is part of the x86_64 baseline
+            build(input, |block| unsafe { classify_sse2(block) })
+        }
+    }
+
+    #[cfg(not(target_arch = "x86_64"))]
+    pub fn new(input: &[u8]) -> Self {
+        Self::scalar(input)
+    }
+
+    /// Byte at a time version, for targets without a SIMD path and to check the SIMD ones against
+    pub fn scalar(input: &[u8]) -> Self {
+        build(input, classify_scalar)
+    }
+
+    /// Offsets of the structurals in ascending order
+    pub fn iter(&self) -> Structurals<'_> {
+        Structurals {
+            words: &self.words,
+            word: 0,
+            bits: self.words.first().copied().unwrap_or(0),
+        }
+    }
+}
+
+pub struct Structurals<'a> {
+    words: &'a [u64],
+    word: usize,
+    bits: u64,
+}
+
+impl Iterator for Structurals<'_> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        while self.bits == 0 {
+            self.word += 1;
+            self.bits = *self.words.get(self.word)?;
+        }
+        let bit = self.bits.trailing_zeros() as usize;
+        self.bits &= self.bits - 1;
+        Some(self.word * BLOCK + bit)
+    }
+}
+
+#[inline(always)]
+fn build(input: &[u8], classify: impl Fn(&[u8; BLOCK]) -> Masks) -> StructuralIndex {
+    let mut words = Vec::with_capacity(input.len().div_ceil(BLOCK));
+    let mut prev_escaped = 0;
+    let mut prev_in_string = 0;
+    let mut blocks = input.chunks_exact(BLOCK);
+    for block in &mut blocks {
+        let masks = classify(block.try_into().unwrap());
+        words.push(structurals(masks, &mut prev_escaped, &mut prev_in_string));
+    }
+    let rest = blocks.remainder();
+    if !rest.is_empty() {
+        let mut last = [b' '; BLOCK];
+        last[..rest.len()].copy_from_slice(rest);
+        let masks = classify(&last);
+        words.push(structurals(masks, &mut prev_escaped, &mut prev_in_string));
+    }
+    StructuralIndex { words }
+}
+
+/// Structurals of one block. The carries hold whether the previous block ended in an escape or inside a string
+#[inline(always)]
+fn structurals(masks: Masks, prev_escaped: &mut u64, prev_in_string: &mut u64) -> u64 {
+    let quotes = masks.quote & !escaped(masks.backslash, prev_escaped);
+    let in_string = prefix_xor(quotes) ^ *prev_in_string;
+    *prev_in_string = ((in_string as i64) >> 63) as u64;
+    (masks.op & !in_string) | quotes
+}
+
+/// Bytes preceded by an odd number of backslashes
+#[inline(always)]
+fn escaped(backslash: u64, prev_escaped: &mut u64) -> u64 {
+    let backslash = backslash & !*prev_escaped;
+    let follows_escape = (backslash << 1) | *prev_escaped;
+    // Runs of backslashes starting on an odd bit, added to themselves, carry out on the bit after the run
+    let odd_starts = backslash & !EVEN_BITS & !follows_escape;
+    let (sequences_on_even, overflow) = odd_starts.overflowing_add(backslash);
+    *prev_escaped = overflow as u64;
+    let invert = sequences_on_even << 1;
+    (EVEN_BITS ^ invert) & follows_escape
+}
+
+/// Bit i is the xor of bits 0..=i, so everything from an opening quote up to the closing one is set
+#[inline(always)]
+fn prefix_xor(mut bits: u64) -> u64 {
+    bits ^= bits << 1;
+    bits ^= bits << 2;
+    bits ^= bits << 4;
+    bits ^= bits << 8;
+    bits ^= bits << 16;
+    bits ^= bits << 32;
+    bits
+}
+
+fn classify_scalar(block: &[u8; BLOCK]) -> Masks {
+    let mut masks = Masks::default();
+    for (i, &b) in block.iter().enumerate() {
+        match b {
+            b'"' => masks.quote |= 1 << i,
+            b'\\' => masks.backslash |= 1 << i,
+            b'{' | b'}' | b'[' | b']' | b':' | b',' => masks.op |= 1 << i,
+            _ => {}
+        }
+    }
+    masks
+}
+
+#[cfg(target_arch = "x86_64")]
+unsafe fn classify_sse2(block: &[u8; BLOCK]) -> Masks {
+    use std::arch::x86_64::*;
+
+    let mut masks = Masks::default();
+    for i in 0..BLOCK / 16 {
+        let chunk = _mm_loadu_si128(block.as_ptr().add(i * 16) as *const __m128i);
+        let eq = |c: u8| _mm_cmpeq_epi8(chunk, _mm_set1_epi8(c as i8));
+        let bits = |v: __m128i| (_mm_movemask_epi8(v) as u32 as u64) << (i * 16);
+        let op = _mm_or_si128(
+            _mm_or_si128(
+                _mm_or_si128(eq(b'{'), eq(b'}')),
+                _mm_or_si128(eq(b'['), eq(b']')),
+            ),
+            _mm_or_si128(eq(b':'), eq(b',')),
+        );
+        masks.quote |= bits(eq(b'"'));
+        masks.backslash |= bits(eq(b'\\'));
+        masks.op |= bits(op);
+    }
+    masks
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+unsafe fn classify_avx2(block: &[u8; BLOCK]) -> Masks {
+    use std::arch::x86_64::*;
+
+    let mut masks = Masks::default();
+    for i in 0..BLOCK / 32 {
+        let chunk = _mm256_loadu_si256(block.as_ptr().add(i * 32) as *const __m256i);
+        let eq = |c: u8| _mm256_cmpeq_epi8(chunk, _mm256_set1_epi8(c as i8));
+        let bits = |v: __m256i| (_mm256_movemask_epi8(v) as u32 as u64) << (i * 32);
+        let op = _mm256_or_si256(
+            _mm256_or_si256(
+                _mm256_or_si256(eq(b'{'), eq(b'}')),
+                _mm256_or_si256(eq(b'['), eq(b']')),
+            ),
+            _mm256_or_si256(eq(b':'), eq(b',')),
+        );
+        masks.quote |= bits(eq(b'"'));
+        masks.backslash |= bits(eq(b'\\'));
+        masks.op |= bits(op);
+    }
+    masks
+}
+
+// Separate entry point so the classifier gets inlined into a loop compiled with AVX2 enabled
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+unsafe fn build_avx2(input: &[u8]) -> StructuralIndex {
+    build(input, |block| unsafe { classify_avx2(block) })
+}
+
+/// Walks the structurals of a document. `pos` is the first byte after the last one consumed
+struct Walker<'a> {
+    input: &'a [u8],
+    structurals: Structurals<'a>,
+    pos: usize,
+    options: ParseOptions,
+}
+
+impl Walker<'_> {
+    fn raw(&mut self) -> Result<usize, DeserializationError> {
+        self.structurals.next().ok_or_else(|| {
+            DeserializationError::new(ErrorKind::TruncatedInput, "Unexpected end of input")
+                .at(self.input, self.input.len())
+        })
+    }
+
+    /// Only whitespace may come between the last structural and `to`
+    fn gap(&self, to: usize, msg: &str) -> Result<(), DeserializationError> {
+        match self.input[self.pos..to]
+            .iter()
+            .position(|b| !b.is_ascii_whitespace())
+        {
+            Some(i) => Err(unexpected_at(self.input, self.pos + i, msg)),
+            None => Ok(()),
+        }
+    }
+
+    /// The next structural, which has to follow the last one with nothing but whitespace in between
+    fn token(&mut self, msg: &str) -> Result<(usize, u8), DeserializationError> {
+        let at = self.raw()?;
+        self.gap(at, msg)?;
+        self.pos = at + 1;
+        Ok((at, self.input[at]))
+    }
+
+    fn expect(&mut self, byte: u8, msg: &str) -> Result<(), DeserializationError> {
+        let (at, b) = self.token(msg)?;
+        if b != byte {
+            return Err(unexpected_at(self.input, at, msg));
+        }
+        Ok(())
+    }
+
+    /// Past the closing quote of a string whose opening quote was just consumed
+    fn skip_string(&mut self) -> Result<(), DeserializationError> {
+        // Nothing inside a string is structural, so the next bit is the closing quote
+        self.pos = self.raw()? + 1;
+        Ok(())
+    }
+
+    /// Past the end of an object or array whose opening bracket was just consumed
+    fn skip_nested(&mut self) -> Result<(), DeserializationError> {
+        let mut depth = 1;
+        while depth != 0 {
+            let at = self.raw()?;
+            match self.input[at] {
+                b'{' | b'[' => depth += 1,
+                b'}' | b']' => depth -= 1,
+                b'"' => {
+                    self.raw()?;
+                }
+                _ => {}
+            }
+            self.pos = at + 1;
+        }
+        Ok(())
+    }
+
+    /// After the opening `{` of a pair object
+    fn pair(&mut self) -> Result<CoordPair, DeserializationError> {
+        let start = self.pos - 1;
+        let mut members = PairMembers::default();
+
+        let (mut at, mut b) = self.token("Expected a member key")?;
+        if b != b'}' {
+            loop {
+                if b != b'"' {
+                    return Err(unexpected_at(self.input, at, "Expected a member key"));
+                }
+                let key_end = self.raw()?;
+                let key = &self.input[at + 1..key_end];
+                let slot = members
+                    .slot(key, self.options)
+                    .map_err(|err| err.at(self.input, at + 1))?;
+                self.pos = key_end + 1;
+                self.expect(b':', "Expected ':' after the member key")?;
+
+                let val_end = self.raw()?;
+                let end = match self.input[val_end] {
+                    // A scalar, everything up to the separator is the value
+                    end @ (b',' | b'}') => {
+                        let text = self.input[self.pos..val_end].trim_ascii();
+                        if text.is_empty() {
+                            return Err(unexpected_at(self.input, val_end, "Expected a value"));
+                        }
+                        if let Some(slot) = slot {
+                            let val = parse_number(text, self.options).ok_or_else(|| {
+                                let offset = text.as_ptr() as usize - self.input.as_ptr() as usize;
+                                bad_number().at(self.input, offset)
+                            })?;
+                            *slot = Some(val);
+                        }
+                        self.pos = val_end + 1;
+                        end
+                    }
+                    open @ (b'"' | b'{' | b'[') => {
+                        self.gap(val_end, "Expected a value")?;
+                        if slot.is_some() {
+                            return Err(bad_number().at(self.input, val_end));
+                        }
+                        self.pos = val_end + 1;
+                        if open == b'"' {
+                            self.skip_string()?;
+                        } else {
+                            self.skip_nested()?;
+                        }
+                        self.token("Expected ',' or '}'")?.1
+                    }
+                    _ => return Err(unexpected_at(self.input, val_end, "Expected a value")),
+                };
+                match end {
+                    b',' => (at, b) = self.token("Expected a member key")?,
+                    b'}' => break,
+                    _ => {
+                        return Err(unexpected_at(
+                            self.input,
+                            self.pos - 1,
+                            "Expected ',' or '}'",
+                        ))
+                    }
+                }
+            }
+        }
+
+        members.finish(|name| missing_member(name).at(self.input, start))
+    }
+}
+
+/// Parses a top level array of pair objects in any layout, through a SIMD built structural index
+pub fn deserialize_structural(input: &[u8]) -> Result<Vec<CoordPair>, DeserializationError> {
+    deserialize_structural_with(input, ParseOptions::default())
+}
+
+pub fn deserialize_structural_with(
+    input: &[u8],
+    options: ParseOptions,
+) -> Result<Vec<CoordPair>, DeserializationError> {
+    bench_block!(handle, "Build Structural Index");
+    let index = StructuralIndex::new(input);
+    record_bytes(input.len() as u64);
+    drop(handle);
+
+    bench_block!("Walk Structurals");
+    let mut walker = Walker {
+        input,
+        structurals: index.iter(),
+        pos: if input.starts_with(b"\xEF\xBB\xBF") {
+            3
+        } else {
+            0
+        },
+        options,
+    };
+    walker.expect(b'[', "Expected '['")?;
+    let mut out = Vec::with_capacity(input.len() / 100);
+    let (mut at, mut b) = walker.token("Expected '{' or ']'")?;
+    if b != b']' {
+        loop {
+            if b != b'{' {
+                return Err(unexpected_at(input, at, "Expected '{'").in_record(out.len()));
+            }
+            let record = out.len();
+            out.push(walker.pair().map_err(|err| err.in_record(record))?);
+            match walker.token("Expected ',' or ']'")? {
+                (_, b',') => (at, b) = walker.token("Expected '{'")?,
+                (_, b']') => break,
+                (at, _) => return Err(unexpected_at(input, at, "Expected ',' or ']'")),
+            }
+        }
+    }
+    walker.gap(input.len(), "Expected the end of the document")?;
+    Ok(out)
+}
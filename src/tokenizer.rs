@@ -0,0 +1,557 @@
+use std::{
+    cell::Cell,
+    io::{self, BufRead, Read},
+};
+
+use crate::{
+    error::{DeserializationError, ErrorKind, Position},
+    parser::{ParseOptions, ParseStats},
+};
+
+// Streaming reader layer for the JSON deserializers. Works on whatever window the inner `BufRead`
+// has filled instead of a byte at a time, and hands out records and tokens as slices of that window.
+// Only a span that straddles a refill gets copied, into a scratch buffer that's reused for the whole stream
+
+/// Reads through to an inner `BufRead`, keeping track of the position of everything consumed
+pub struct Tokenizer<R> {
+    inner: R,
+    position: Position,
+    scratch: Vec<u8>,
+    options: ParseOptions,
+    /// Where the contents of the last key `next_member` read start
+    key: Position,
+    stats: ParseStats,
+}
+
+/// Where `Tokenizer::with_record` ended a record
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum RecordEnd {
+    /// Its brackets closed
+    Closed,
+    /// Before the `{` of the next record, so this one was cut short. The `,` before it is part of the span
+    NextRecord,
+    /// Before a closing bracket it didn't open, which is left for the array around it
+    Unopened,
+    /// Somewhere in the middle, after as much as it was allowed
+    TooLong,
+}
+
+/// What `Tokenizer::next_member` found
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Member {
+    /// A member whose key is at this index of the known keys, the value comes next
+    Known(usize),
+    /// A member with any other key, the value comes next
+    Unknown,
+    /// The closing `}`, which has been consumed
+    End,
+}
+
+impl<R: BufRead> Tokenizer<R> {
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            position: Position::default(),
+            scratch: Vec::new(),
+            options: ParseOptions::default(),
+            key: Position::default(),
+            stats: ParseStats::default(),
+        }
+    }
+
+    pub fn with_options(mut self, options: ParseOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    pub fn options(&self) -> ParseOptions {
+        self.options
+    }
+
+    pub fn position(&self) -> Position {
+        self.position
+    }
+
+    /// What's been read so far. Records are only counted by the array reader
+    pub fn stats(&self) -> ParseStats {
+        ParseStats {
+            bytes: self.position.offset,
+            ..self.stats
+        }
+    }
+
+    pub(crate) fn stats_mut(&mut self) -> &mut ParseStats {
+        &mut self.stats
+    }
+
+    /// Error about the last key `next_member` read, which is `member` spelled as in the JSON, being a repeat
+    pub fn duplicate_member(&self, member: &str) -> DeserializationError {
+        DeserializationError::duplicate_member(member)
+            .at(member.as_bytes(), 0)
+            .after(self.key)
+    }
+
+    /// Places `err` at the current position, with the buffered input that follows as the snippet
+    pub fn locate(&mut self, err: DeserializationError) -> DeserializationError {
+        let position = self.position;
+        match self.inner.fill_buf() {
+            Ok(buf) => err.at(buf, 0).after(position),
+            Err(_) => err.after(position),
+        }
+    }
+
+    /// Error about the next byte, or about the input ending here
+    pub fn unexpected(&mut self, msg: &str) -> DeserializationError {
+        let kind = match self.peek() {
+            Ok(Some(b)) => ErrorKind::UnexpectedByte(b),
+            Ok(None) => ErrorKind::TruncatedInput,
+            Err(err) => return err.into(),
+        };
+        self.locate(DeserializationError::new(kind, msg))
+    }
+
+    /// The next byte without consuming it, `None` at the end of the input
+    pub fn peek(&mut self) -> io::Result<Option<u8>> {
+        Ok(self.inner.fill_buf()?.first().copied())
+    }
+
+    /// Consumes whitespace, then peeks at the byte after it
+    pub fn skip_whitespace(&mut self) -> io::Result<Option<u8>> {
+        loop {
+            let buf = self.inner.fill_buf()?;
+            let Some(&first) = buf.first() else {
+                return Ok(None);
+            };
+            if !first.is_ascii_whitespace() {
+                return Ok(Some(first));
+            }
+            let len = buf
+                .iter()
+                .position(|b| !b.is_ascii_whitespace())
+                .unwrap_or(buf.len());
+            self.position.advance(&buf[..len]);
+            self.inner.consume(len);
+            self.stats.whitespace_bytes += len as u64;
+        }
+    }
+
+    /// Skips whitespace and consumes `byte`, which has to come next
+    pub fn expect(&mut self, byte: u8, msg: &str) -> Result<(), DeserializationError> {
+        if self.skip_whitespace()? != Some(byte) {
+            return Err(self.unexpected(msg));
+        }
+        self.consume(1);
+        Ok(())
+    }
+
+    /// Hands `f` the buffered input without consuming it. `f` returns how much of it it used, which is then
+    /// consumed, or `None` to leave all of it for a slower path, like when what it wants straddles the window
+    pub fn with_buffered<T>(
+        &mut self,
+        f: impl FnOnce(&[u8]) -> Option<(usize, T)>,
+    ) -> io::Result<Option<T>> {
+        let buf = self.inner.fill_buf()?;
+        let Some((len, out)) = f(buf) else {
+            return Ok(None);
+        };
+        self.position.advance(&buf[..len]);
+        self.inner.consume(len);
+        Ok(Some(out))
+    }
+
+    /// Passes the span `scan` finds to `f` and consumes it. `scan` is handed the buffered input a window
+    /// at a time and returns the length of the span within the window it ends in.
+    /// At the end of the input `f` gets what's left, and `false` to say the span never ended
+    fn with_scanned<T>(
+        &mut self,
+        mut scan: impl FnMut(&[u8]) -> Option<usize>,
+        f: impl FnOnce(&[u8], bool) -> T,
+    ) -> io::Result<T> {
+        let buf = self.inner.fill_buf()?;
+        if let Some(len) = scan(buf) {
+            let out = f(&buf[..len], true);
+            self.position.advance(&buf[..len]);
+            self.inner.consume(len);
+            return Ok(out);
+        }
+        // Straddles the end of the window
+        self.scratch.clear();
+        self.scratch.extend_from_slice(buf);
+        self.position.advance(buf);
+        let len = buf.len();
+        self.inner.consume(len);
+        let mut done = false;
+        while !done {
+            let buf = self.inner.fill_buf()?;
+            if buf.is_empty() {
+                break;
+            }
+            let len = match scan(buf) {
+                Some(len) => {
+                    done = true;
+                    len
+                }
+                None => buf.len(),
+            };
+            self.scratch.extend_from_slice(&buf[..len]);
+            self.position.advance(&buf[..len]);
+            self.inner.consume(len);
+        }
+        Ok(f(&self.scratch, done))
+    }
+
+    /// Runs `f` on a span, placing its errors, which are relative to the span, in the whole input
+    fn with_span<T>(
+        &mut self,
+        scan: impl FnMut(&[u8]) -> Option<usize>,
+        f: impl FnOnce(&[u8], bool) -> Result<T, DeserializationError>,
+    ) -> Result<T, DeserializationError> {
+        let start = self.position;
+        self.with_scanned(scan, f)?.map_err(|err| err.after(start))
+    }
+
+    /// Passes the next scalar, a number or a literal like `true`, to `f`.
+    /// Errors `f` returns are taken to be relative to the start of the token
+    pub fn with_token<T>(
+        &mut self,
+        f: impl FnOnce(&[u8]) -> Result<T, DeserializationError>,
+    ) -> Result<T, DeserializationError> {
+        if let None | Some(b',' | b'}' | b']' | b':') = self.skip_whitespace()? {
+            return Err(self.unexpected("Expected a value"));
+        }
+        self.with_span(
+            |buf| {
+                buf.iter().position(|&b| {
+                    matches!(b, b',' | b'}' | b']' | b':') || b.is_ascii_whitespace()
+                })
+            },
+            |token, _| f(token),
+        )
+    }
+
+    /// Passes the raw contents of the next string, escapes and all, to `f`.
+    /// Errors `f` returns are taken to be relative to the start of the contents
+    pub fn with_string<T>(
+        &mut self,
+        f: impl FnOnce(&[u8]) -> Result<T, DeserializationError>,
+    ) -> Result<T, DeserializationError> {
+        if self.skip_whitespace()? != Some(b'"') {
+            return Err(self.unexpected("Expected a string"));
+        }
+        let mut escaped = false;
+        let mut opening = true;
+        self.with_span(
+            |buf| {
+                for (i, &b) in buf.iter().enumerate() {
+                    if std::mem::take(&mut opening) {
+                        continue;
+                    }
+                    match b {
+                        _ if escaped => escaped = false,
+                        b'\\' => escaped = true,
+                        b'"' => return Some(i + 1),
+                        _ => {}
+                    }
+                }
+                None
+            },
+            |span, closed| match span {
+                [b'"', contents @ .., b'"'] if closed && span.len() >= 2 => {
+                    let mut opening_quote = Position::default();
+                    opening_quote.advance(b"\"");
+                    f(contents).map_err(|err| err.after(opening_quote))
+                }
+                _ => Err(DeserializationError::new(
+                    ErrorKind::TruncatedInput,
+                    "Expected the closing '\"'",
+                )
+                .at(span, span.len())),
+            },
+        )
+    }
+
+    /// Consumes the next value whatever it is, strings and nested objects and arrays included
+    pub fn skip_value(&mut self) -> Result<(), DeserializationError> {
+        match self.skip_whitespace()? {
+            Some(b'"') => self.with_string(|_| Ok(()))?,
+            _ => self.with_value(|_| Ok(()))?,
+        }
+        match self.peek()? {
+            // Anything but a delimiter right after means it wasn't a single value
+            Some(b) if !matches!(b, b',' | b'}' | b']') && !b.is_ascii_whitespace() => {
+                Err(self.unexpected("Expected ',' or the end of the value"))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Passes the raw bytes of the next value, whatever it is, to `f`. Objects and arrays are matched up to
+    /// their closing bracket without being parsed. Errors `f` returns are taken to be relative to the start of the value
+    pub fn with_value<T>(
+        &mut self,
+        f: impl FnOnce(&[u8]) -> Result<T, DeserializationError>,
+    ) -> Result<T, DeserializationError> {
+        if !matches!(self.skip_whitespace()?, Some(b'"' | b'{' | b'[')) {
+            return self.with_token(f);
+        }
+        let (mut depth, mut in_string, mut escaped) = (0usize, false, false);
+        self.with_span(
+            |buf| {
+                for (i, &b) in buf.iter().enumerate() {
+                    match b {
+                        _ if escaped => escaped = false,
+                        b'\\' if in_string => escaped = true,
+                        b'"' => {
+                            in_string = !in_string;
+                            if !in_string && depth == 0 {
+                                return Some(i + 1);
+                            }
+                        }
+                        _ if in_string => {}
+                        b'{' | b'[' => depth += 1,
+                        b'}' | b']' => {
+                            depth -= 1;
+                            if depth == 0 {
+                                return Some(i + 1);
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+                None
+            },
+            |span, closed| match closed {
+                true => f(span),
+                false => Err(DeserializationError::new(
+                    ErrorKind::TruncatedInput,
+                    "Expected the end of the value",
+                )
+                .at(span, span.len())),
+            },
+        )
+    }
+
+    /// Passes the next value to `f` like `with_value`, for a record that may be broken, without trusting its
+    /// brackets to balance. Besides where its brackets close, the span also ends before a `{` that follows a
+    /// `,` in an object, where only a key can come, and before a `{` that follows `}` and `,` in a string,
+    /// which is most likely one that was left open. Either is taken to be the start of the next record. It
+    /// also ends before a closing bracket it never opened and after `max_len` bytes. `f` only gets spans
+    /// whose brackets closed, any other span is an error
+    pub(crate) fn with_record<T>(
+        &mut self,
+        max_len: usize,
+        f: impl FnOnce(&[u8]) -> Result<T, DeserializationError>,
+    ) -> (Result<T, DeserializationError>, RecordEnd) {
+        match self.skip_whitespace() {
+            Ok(Some(b'"' | b'{' | b'[')) => {}
+            Ok(_) => return (self.with_token(f), RecordEnd::Closed),
+            Err(err) => return (Err(err.into()), RecordEnd::Closed),
+        }
+        let end = Cell::new(RecordEnd::Closed);
+        let stop = Cell::new(0);
+        let mut seen = 0;
+        // A bit per level of nesting, set for objects
+        let (mut depth, mut objects) = (0u32, 0u64);
+        let (mut in_string, mut escaped) = (false, false);
+        // A `,` in an object and only whitespace since, or in a string, how much of `}` and `,` there's been
+        let (mut comma, mut brace) = (false, 0);
+        let res = self.with_span(
+            |buf| {
+                let allowed = buf.len().min(max_len - seen);
+                for (i, &b) in buf[..allowed].iter().enumerate() {
+                    let cut = |reason| {
+                        end.set(reason);
+                        stop.set(b);
+                        Some(i)
+                    };
+                    if in_string {
+                        match b {
+                            _ if std::mem::take(&mut escaped) => brace = 0,
+                            b'\\' => escaped = true,
+                            b'"' => {
+                                in_string = false;
+                                if depth == 0 {
+                                    return Some(i + 1);
+                                }
+                            }
+                            b'}' => brace = 1,
+                            b',' if brace == 1 => brace = 2,
+                            b'{' if brace == 2 => return cut(RecordEnd::NextRecord),
+                            _ if b.is_ascii_whitespace() => {}
+                            _ => brace = 0,
+                        }
+                        continue;
+                    }
+                    if b.is_ascii_whitespace() {
+                        continue;
+                    }
+                    let after_comma = std::mem::take(&mut comma);
+                    brace = 0;
+                    match b {
+                        b'"' => in_string = true,
+                        b'{' if after_comma => return cut(RecordEnd::NextRecord),
+                        b'{' | b'[' if depth == u64::BITS => return cut(RecordEnd::TooLong),
+                        b'{' | b'[' => {
+                            objects = (objects & !(1 << depth)) | (u64::from(b == b'{') << depth);
+                            depth += 1;
+                        }
+                        b'}' | b']' => {
+                            let object = b == b'}';
+                            // A stray opening bracket inside is closed along with the one around it
+                            match (0..depth).rev().find(|&level| ((objects >> level) & 1 == 1) == object) {
+                                Some(level) => depth = level,
+                                None => return cut(RecordEnd::Unopened),
+                            }
+                            if depth == 0 {
+                                return Some(i + 1);
+                            }
+                        }
+                        b',' => comma = depth > 0 && (objects >> (depth - 1)) & 1 == 1,
+                        _ => {}
+                    }
+                }
+                if allowed < buf.len() {
+                    end.set(RecordEnd::TooLong);
+                    stop.set(buf[allowed]);
+                    return Some(allowed);
+                }
+                seen += buf.len();
+                None
+            },
+            |span, closed| {
+                let msg = match (closed, end.get()) {
+                    (false, _) => {
+                        return Err(DeserializationError::new(
+                            ErrorKind::TruncatedInput,
+                            "Expected the end of the record",
+                        )
+                        .at(span, span.len()))
+                    }
+                    (true, RecordEnd::Closed) => return f(span),
+                    (true, RecordEnd::NextRecord) => {
+                        "Expected the end of the record before the next one".to_string()
+                    }
+                    (true, RecordEnd::Unopened) => {
+                        "Expected the end of the record before a bracket it didn't open".to_string()
+                    }
+                    (true, RecordEnd::TooLong) => format!(
+                        "Expected the end of the record within {max_len} bytes and {} levels of nesting",
+                        u64::BITS
+                    ),
+                };
+                Err(DeserializationError::new(ErrorKind::UnexpectedByte(stop.get()), msg)
+                    .at(span, span.len()))
+            },
+        );
+        (res, end.get())
+    }
+
+    /// Skips ahead to the start of the next record, a `{` after a `,`, and consumes the `,`. Brackets and strings
+    /// aren't trusted, since whatever is being skipped is broken. `false` when the input ends after a `]` and
+    /// whitespace instead, which is taken to close the array
+    pub(crate) fn skip_to_record(&mut self) -> Result<bool, DeserializationError> {
+        let (mut comma, mut closed) = (false, false);
+        loop {
+            let buf = self.inner.fill_buf()?;
+            if buf.is_empty() {
+                return match closed {
+                    true => Ok(false),
+                    false => Err(self.locate(DeserializationError::new(
+                        ErrorKind::TruncatedInput,
+                        "Expected another record or the end of the array",
+                    ))),
+                };
+            }
+            let mut found = None;
+            for (i, &b) in buf.iter().enumerate() {
+                if b.is_ascii_whitespace() {
+                    continue;
+                }
+                if b == b'{' && comma {
+                    found = Some(i);
+                    break;
+                }
+                comma = b == b',';
+                closed = b == b']';
+            }
+            let len = found.unwrap_or(buf.len());
+            self.position.advance(&buf[..len]);
+            self.inner.consume(len);
+            if found.is_some() {
+                return Ok(true);
+            }
+        }
+    }
+
+    /// Steps to the next member of an object whose `{` has been consumed, and through its `:`.
+    /// `keys` are compared against the raw key, so they have to be spelled as they are in the JSON.
+    /// `first` tracks whether a `,` is needed and should start out `true`. Parsing strictly, any other key is an error
+    pub fn next_member(
+        &mut self,
+        keys: &[&[u8]],
+        first: &mut bool,
+    ) -> Result<Member, DeserializationError> {
+        match self.skip_whitespace()? {
+            Some(b'}') => {
+                self.consume(1);
+                return Ok(Member::End);
+            }
+            Some(b',') if !*first => self.consume(1),
+            _ if *first => {}
+            _ => return Err(self.unexpected("Expected ',' or '}'")),
+        }
+        *first = false;
+        let strict = self.options.strict;
+        // The contents start just past the quote
+        self.skip_whitespace()?;
+        self.key = self.position;
+        self.key.advance(b"\"");
+        let member = self.with_string(|key| match keys.iter().position(|&known| known == key) {
+            Some(index) => Ok(Member::Known(index)),
+            None if strict => {
+                let err = DeserializationError::unknown_member(&String::from_utf8_lossy(key));
+                Err(err.at(key, 0))
+            }
+            None => Ok(Member::Unknown),
+        })?;
+        self.expect(b':', "Expected ':' after the member key")?;
+        if member == Member::Unknown {
+            self.stats.ignored_keys += 1;
+        }
+        Ok(member)
+    }
+
+    /// Steps past the `,` before the next element of an array whose `[` has been consumed. `false` at
+    /// the closing `]`, which is left for the caller. `first` works like it does for `next_member`
+    pub fn has_element(&mut self, first: &mut bool) -> Result<bool, DeserializationError> {
+        match self.skip_whitespace()? {
+            Some(b']') => return Ok(false),
+            Some(b',') if !*first => self.consume(1),
+            _ if *first => {}
+            _ => return Err(self.unexpected("Expected ',' or ']'")),
+        }
+        *first = false;
+        Ok(true)
+    }
+}
+
+impl<R: BufRead> Read for Tokenizer<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.position.advance(&buf[..read]);
+        Ok(read)
+    }
+}
+
+impl<R: BufRead> BufRead for Tokenizer<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.inner.fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        if let Ok(buf) = self.inner.fill_buf() {
+            self.position.advance(&buf[..amt.min(buf.len())]);
+        }
+        self.inner.consume(amt);
+    }
+}
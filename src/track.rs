@@ -0,0 +1,182 @@
+use std::io::Write;
+
+use clap::Args;
+use rand::{RngCore, SeedableRng};
+
+use crate::{
+    calc::{destination, EARTH_RADIUS},
+    region::wrap_lon,
+    rng::{gen_normal, gen_range, gen_unit, Xoshiro256StarStar},
+    CoordPair,
+};
+
+// Random walk GPS tracks. Each step moves the true position along a great circle at a jittered speed,
+// then reports it with gaussian noise, so consecutive points are strongly correlated like real trajectories
+
+#[derive(Debug, Clone, Args)]
+pub struct TrackParams {
+    /// Mean speed in m/s
+    #[arg(long, default_value_t = 13.9)]
+    pub speed: f64,
+    /// Standard deviation of the heading change per sample, in degrees
+    #[arg(long, default_value_t = 5.0)]
+    pub heading_drift: f64,
+    /// Seconds between samples
+    #[arg(long, default_value_t = 1.0)]
+    pub interval: f64,
+    /// Standard deviation of the GPS error in metres
+    #[arg(long, default_value_t = 3.0)]
+    pub gps_noise: f64,
+    /// Number of points in each track
+    #[arg(long, default_value_t = 1000)]
+    pub track_len: usize,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct TrackPoint {
+    pub track: usize,
+    pub lat: f64,
+    pub lon: f64,
+}
+
+pub struct TrackGen<T = Xoshiro256StarStar> {
+    params: TrackParams,
+    rng: T,
+    track: usize,
+    point_in_track: usize,
+    lat: f64,
+    lon: f64,
+    heading: f64,
+}
+
+impl TrackGen {
+    pub fn from_seed(seed: u64, params: TrackParams) -> Self {
+        Self::new(Xoshiro256StarStar::seed_from_u64(seed), params)
+    }
+}
+
+impl<T: RngCore> TrackGen<T> {
+    pub fn new(rng: T, params: TrackParams) -> Self {
+        Self {
+            params,
+            rng,
+            track: 0,
+            point_in_track: 0,
+            lat: 0.0,
+            lon: 0.0,
+            heading: 0.0,
+        }
+    }
+
+    /// Consecutive points of each track as pairs, `count` in total. No pair spans two tracks
+    pub fn segments(self, count: usize) -> Segments<T> {
+        Segments {
+            points: self,
+            prev: None,
+            remaining: count,
+        }
+    }
+
+    fn start_new_track(&mut self) {
+        // Uniform over the sphere's area rather than over lat/lon
+        self.lat = (2.0 * gen_unit(&mut self.rng) - 1.0).asin().to_degrees();
+        self.lon = gen_range(&mut self.rng, -180.0, 180.0);
+        self.heading = gen_range(&mut self.rng, 0.0, 360.0);
+    }
+
+    fn step(&mut self) {
+        self.heading += gen_normal(&mut self.rng) * self.params.heading_drift;
+        let speed = (self.params.speed * (1.0 + 0.1 * gen_normal(&mut self.rng))).max(0.0);
+        let delta = speed * self.params.interval / 1000.0 / EARTH_RADIUS;
+        (self.lat, self.lon) = destination(self.lat, self.lon, self.heading.to_radians(), delta);
+    }
+
+    fn observe(&mut self) -> (f64, f64) {
+        let north = gen_normal(&mut self.rng) * self.params.gps_noise / 1000.0;
+        let east = gen_normal(&mut self.rng) * self.params.gps_noise / 1000.0;
+        let lat = (self.lat + (north / EARTH_RADIUS).to_degrees()).clamp(-90.0, 90.0);
+        let lon_scale = self.lat.to_radians().cos().max(1e-9);
+        let lon = wrap_lon(self.lon + (east / (EARTH_RADIUS * lon_scale)).to_degrees());
+        (lat, lon)
+    }
+}
+
+impl<T: RngCore> Iterator for TrackGen<T> {
+    type Item = TrackPoint;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.point_in_track == 0 {
+            self.start_new_track();
+        } else {
+            self.step();
+        }
+        let (lat, lon) = self.observe();
+        let point = TrackPoint {
+            track: self.track,
+            lat,
+            lon,
+        };
+        self.point_in_track += 1;
+        if self.point_in_track >= self.params.track_len.max(2) {
+            self.point_in_track = 0;
+            self.track += 1;
+        }
+        Some(point)
+    }
+}
+
+pub struct Segments<T> {
+    points: TrackGen<T>,
+    prev: Option<TrackPoint>,
+    remaining: usize,
+}
+
+impl<T: RngCore> Iterator for Segments<T> {
+    type Item = CoordPair;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        loop {
+            let point = self.points.next()?;
+            let prev = self.prev.replace(point);
+            if let Some(prev) = prev.filter(|prev| prev.track == point.track) {
+                self.remaining -= 1;
+                return Some(((prev.lat, prev.lon), (point.lat, point.lon)).into());
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        // Tracks never run out
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+/// Writes `[[{"lat":..,"lon":..},..],..]`, one inner array per track
+pub fn write_tracks_json(
+    points: impl Iterator<Item = TrackPoint>,
+    writer: &mut impl Write,
+) -> Result<(), std::io::Error> {
+    let mut buf = ryu::Buffer::new();
+    let mut cur_track = None;
+    writer.write_all(b"[")?;
+    for point in points {
+        match cur_track {
+            None => writer.write_all(b"[")?,
+            Some(track) if track != point.track => writer.write_all(b"],[")?,
+            Some(_) => writer.write_all(b",")?,
+        }
+        cur_track = Some(point.track);
+        writer.write_all(b"{\"lat\":")?;
+        writer.write_all(buf.format(point.lat).as_bytes())?;
+        writer.write_all(b",\"lon\":")?;
+        writer.write_all(buf.format(point.lon).as_bytes())?;
+        writer.write_all(b"}")?;
+    }
+    if cur_track.is_some() {
+        writer.write_all(b"]")?;
+    }
+    writer.write_all(b"]")
+}
@@ -0,0 +1,415 @@
+use std::io::{BufRead, Write};
+
+use crate::{
+    error::{DeserializationError, ErrorKind},
+    float::is_json_number,
+    parser::{bad_number, parse_number, write_json_array, Deserializable, Serializable},
+    tokenizer::Tokenizer,
+};
+
+// Serializable and Deserializable for the standard types, so derived structs can have them as fields and
+// anything built out of them can be written as JSON. Options are `null` when empty, sequences, tuples and
+// fixed size arrays are JSON arrays
+
+/// Reading a value on its own starts a tokenizer over the reader
+macro_rules! streaming_via_tokenizer {
+    () => {
+        fn streaming_deserialize(reader: &mut impl BufRead) -> Result<Self, DeserializationError> {
+            Self::tokenized_deserialize(&mut Tokenizer::new(reader))
+        }
+    };
+}
+
+/// JSON has no NaN or infinity, and writing ryu's `NaN` or `inf` would leave a file nothing can read back
+fn non_finite() -> std::io::Error {
+    std::io::Error::new(
+        std::io::ErrorKind::InvalidInput,
+        "NaN and infinity can't be written as JSON",
+    )
+}
+
+impl Serializable for f64 {
+    fn streaming_serialize(&mut self, writer: &mut impl Write) -> Result<(), std::io::Error> {
+        if !self.is_finite() {
+            return Err(non_finite());
+        }
+        writer.write_all(ryu::Buffer::new().format_finite(*self).as_bytes())
+    }
+}
+
+impl Deserializable for f64 {
+    streaming_via_tokenizer!();
+
+    fn tokenized_deserialize<R: BufRead>(
+        reader: &mut Tokenizer<R>,
+    ) -> Result<Self, DeserializationError> {
+        let options = reader.options();
+        reader.with_token(|token| {
+            parse_number(token, options).ok_or_else(|| bad_number().at(token, 0))
+        })
+    }
+}
+
+impl Serializable for f32 {
+    fn streaming_serialize(&mut self, writer: &mut impl Write) -> Result<(), std::io::Error> {
+        if !self.is_finite() {
+            return Err(non_finite());
+        }
+        writer.write_all(ryu::Buffer::new().format_finite(*self).as_bytes())
+    }
+}
+
+impl Deserializable for f32 {
+    streaming_via_tokenizer!();
+
+    fn tokenized_deserialize<R: BufRead>(
+        reader: &mut Tokenizer<R>,
+    ) -> Result<Self, DeserializationError> {
+        let strict = reader.options().strict;
+        reader.with_token(|token| {
+            // Straight to f32, going through an f64 could round twice
+            std::str::from_utf8(token)
+                .ok()
+                .filter(|_| !strict || is_json_number(token))
+                .and_then(|token| token.parse().ok())
+                .ok_or_else(|| bad_number().at(token, 0))
+        })
+    }
+}
+
+macro_rules! integer_impls {
+    ($($ty:ty),*) => {$(
+        impl Serializable for $ty {
+            fn streaming_serialize(&mut self, writer: &mut impl Write) -> Result<(), std::io::Error> {
+                write!(writer, "{}", self)
+            }
+        }
+
+        impl Deserializable for $ty {
+            streaming_via_tokenizer!();
+
+            fn tokenized_deserialize<R: BufRead>(
+                reader: &mut Tokenizer<R>,
+            ) -> Result<Self, DeserializationError> {
+                reader.with_token(|token| {
+                    std::str::from_utf8(token)
+                        .ok()
+                        .and_then(|token| token.parse().ok())
+                        .ok_or_else(|| {
+                            DeserializationError::new(
+                                ErrorKind::BadNumber,
+                                concat!("Can't parse ", stringify!($ty), " value"),
+                            )
+                            .at(token, 0)
+                        })
+                })
+            }
+        }
+    )*};
+}
+
+integer_impls!(i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize);
+
+impl Serializable for bool {
+    fn streaming_serialize(&mut self, writer: &mut impl Write) -> Result<(), std::io::Error> {
+        writer.write_all(if *self { b"true" } else { b"false" })
+    }
+}
+
+impl Deserializable for bool {
+    streaming_via_tokenizer!();
+
+    fn tokenized_deserialize<R: BufRead>(
+        reader: &mut Tokenizer<R>,
+    ) -> Result<Self, DeserializationError> {
+        reader.with_token(|token| match token {
+            b"true" => Ok(true),
+            b"false" => Ok(false),
+            _ => Err(DeserializationError::new(
+                ErrorKind::UnexpectedByte(token[0]),
+                "Expected a bool",
+            )
+            .at(token, 0)),
+        })
+    }
+}
+
+impl Serializable for str {
+    fn streaming_serialize(&mut self, writer: &mut impl Write) -> Result<(), std::io::Error> {
+        writer.write_all(b"\"")?;
+        let bytes = self.as_bytes();
+        let mut start = 0;
+        for (i, &b) in bytes.iter().enumerate() {
+            let escape: &[u8] = match b {
+                b'"' => b"\\\"",
+                b'\\' => b"\\\\",
+                b'\n' => b"\\n",
+                b'\r' => b"\\r",
+                b'\t' => b"\\t",
+                0..=0x1F => b"",
+                _ => continue,
+            };
+            writer.write_all(&bytes[start..i])?;
+            if escape.is_empty() {
+                write!(writer, "\\u{:04x}", b)?;
+            } else {
+                writer.write_all(escape)?;
+            }
+            start = i + 1;
+        }
+        writer.write_all(&bytes[start..])?;
+        writer.write_all(b"\"")
+    }
+}
+
+impl Serializable for String {
+    fn streaming_serialize(&mut self, writer: &mut impl Write) -> Result<(), std::io::Error> {
+        self.as_mut_str().streaming_serialize(writer)
+    }
+}
+
+impl Deserializable for String {
+    streaming_via_tokenizer!();
+
+    fn tokenized_deserialize<R: BufRead>(
+        reader: &mut Tokenizer<R>,
+    ) -> Result<Self, DeserializationError> {
+        reader.with_string(unescape)
+    }
+}
+
+fn bad_string(raw: &[u8], offset: usize, msg: &str) -> DeserializationError {
+    let kind = match raw.get(offset) {
+        Some(&b) => ErrorKind::UnexpectedByte(b),
+        None => ErrorKind::TruncatedInput,
+    };
+    DeserializationError::new(kind, msg).at(raw, offset)
+}
+
+/// The contents of a JSON string literal, between the quotes
+fn unescape(raw: &[u8]) -> Result<String, DeserializationError> {
+    let mut out = Vec::with_capacity(raw.len());
+    let mut i = 0;
+    while i < raw.len() {
+        let Some(escape) = raw[i..].iter().position(|&b| b == b'\\') else {
+            out.extend_from_slice(&raw[i..]);
+            break;
+        };
+        out.extend_from_slice(&raw[i..i + escape]);
+        i += escape + 1;
+        let unescaped = match raw.get(i) {
+            Some(b'"') => b'"',
+            Some(b'\\') => b'\\',
+            Some(b'/') => b'/',
+            Some(b'b') => 0x08,
+            Some(b'f') => 0x0C,
+            Some(b'n') => b'\n',
+            Some(b'r') => b'\r',
+            Some(b't') => b'\t',
+            Some(b'u') => {
+                let (c, len) = unicode_escape(raw, i + 1)?;
+                out.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+                i += 1 + len;
+                continue;
+            }
+            _ => return Err(bad_string(raw, i, "Invalid escape")),
+        };
+        out.push(unescaped);
+        i += 1;
+    }
+    String::from_utf8(out).map_err(|err| {
+        // Escapes only ever produce valid UTF-8, so the bad byte is somewhere in the unescaped runs
+        let valid = err.utf8_error().valid_up_to();
+        DeserializationError::new(
+            ErrorKind::UnexpectedByte(err.as_bytes()[valid]),
+            "Invalid UTF-8",
+        )
+    })
+}
+
+/// `XXXX` after a `\u` at `start`, or a surrogate pair `XXXX\uXXXX`, and how many bytes it took
+fn unicode_escape(raw: &[u8], start: usize) -> Result<(char, usize), DeserializationError> {
+    let hex = |at: usize| {
+        raw.get(at..at + 4)
+            .filter(|digits| digits.iter().all(u8::is_ascii_hexdigit))
+            .and_then(|digits| std::str::from_utf8(digits).ok())
+            .and_then(|digits| u32::from_str_radix(digits, 16).ok())
+            .ok_or_else(|| bad_string(raw, at, "Expected 4 hex digits"))
+    };
+    let first = hex(start)?;
+    if !(0xD800..0xDC00).contains(&first) {
+        let c = char::from_u32(first).ok_or_else(|| bad_string(raw, start, "Lone surrogate"))?;
+        return Ok((c, 4));
+    }
+    if raw.get(start + 4..start + 6) != Some(b"\\u") {
+        return Err(bad_string(raw, start + 4, "Expected the low surrogate"));
+    }
+    let second = hex(start + 6)?;
+    if !(0xDC00..0xE000).contains(&second) {
+        return Err(bad_string(raw, start + 6, "Expected the low surrogate"));
+    }
+    let c = char::from_u32(0x10000 + ((first - 0xD800) << 10) + (second - 0xDC00)).unwrap();
+    Ok((c, 10))
+}
+
+impl<T: Serializable + ?Sized> Serializable for &mut T {
+    fn streaming_serialize(&mut self, writer: &mut impl Write) -> Result<(), std::io::Error> {
+        (**self).streaming_serialize(writer)
+    }
+}
+
+impl<T: Serializable> Serializable for Option<T> {
+    fn streaming_serialize(&mut self, writer: &mut impl Write) -> Result<(), std::io::Error> {
+        match self {
+            Some(val) => val.streaming_serialize(writer),
+            None => writer.write_all(b"null"),
+        }
+    }
+}
+
+impl<T: Deserializable> Deserializable for Option<T> {
+    streaming_via_tokenizer!();
+
+    fn tokenized_deserialize<R: BufRead>(
+        reader: &mut Tokenizer<R>,
+    ) -> Result<Self, DeserializationError> {
+        if reader.skip_whitespace()? != Some(b'n') {
+            return T::tokenized_deserialize(reader).map(Some);
+        }
+        reader.with_token(|token| match token {
+            b"null" => Ok(None),
+            _ => Err(DeserializationError::new(
+                ErrorKind::UnexpectedByte(token[0]),
+                "Expected null or a value",
+            )
+            .at(token, 0)),
+        })
+    }
+}
+
+impl<T: Serializable> Serializable for [T] {
+    fn streaming_serialize(&mut self, writer: &mut impl Write) -> Result<(), std::io::Error> {
+        write_json_array(self.iter_mut(), writer)
+    }
+}
+
+impl<T: Serializable> Serializable for Vec<T> {
+    fn streaming_serialize(&mut self, writer: &mut impl Write) -> Result<(), std::io::Error> {
+        self.as_mut_slice().streaming_serialize(writer)
+    }
+}
+
+impl<T: Serializable, const N: usize> Serializable for [T; N] {
+    fn streaming_serialize(&mut self, writer: &mut impl Write) -> Result<(), std::io::Error> {
+        self.as_mut_slice().streaming_serialize(writer)
+    }
+}
+
+/// Reads an array that has to have exactly `len` elements
+struct FixedArray {
+    len: usize,
+    first: bool,
+}
+
+impl FixedArray {
+    fn open<R: BufRead>(
+        reader: &mut Tokenizer<R>,
+        len: usize,
+    ) -> Result<Self, DeserializationError> {
+        reader.expect(b'[', "Expected '['")?;
+        Ok(Self { len, first: true })
+    }
+
+    fn wrong_len<R: BufRead>(&self, reader: &mut Tokenizer<R>) -> DeserializationError {
+        reader.unexpected(&format!("Expected an array of {} elements", self.len))
+    }
+
+    /// Steps to the next element, which has to be there
+    fn element<R: BufRead>(
+        &mut self,
+        reader: &mut Tokenizer<R>,
+    ) -> Result<(), DeserializationError> {
+        match reader.has_element(&mut self.first)? {
+            true => Ok(()),
+            false => Err(self.wrong_len(reader)),
+        }
+    }
+
+    /// Consumes the `]`, which has to come after the last element
+    fn close<R: BufRead>(mut self, reader: &mut Tokenizer<R>) -> Result<(), DeserializationError> {
+        if reader.has_element(&mut self.first)? {
+            return Err(self.wrong_len(reader));
+        }
+        reader.consume(1);
+        Ok(())
+    }
+}
+
+impl<T: Deserializable, const N: usize> Deserializable for [T; N] {
+    streaming_via_tokenizer!();
+
+    fn tokenized_deserialize<R: BufRead>(
+        reader: &mut Tokenizer<R>,
+    ) -> Result<Self, DeserializationError> {
+        let mut array = FixedArray::open(reader, N)?;
+        let mut elements = Vec::with_capacity(N);
+        for _ in 0..N {
+            array.element(reader)?;
+            elements.push(T::tokenized_deserialize(reader)?);
+        }
+        array.close(reader)?;
+        Ok(match elements.try_into() {
+            Ok(elements) => elements,
+            Err(_) => unreachable!("read exactly N elements"),
+        })
+    }
+}
+
+macro_rules! tuple_impls {
+    ($($len:literal: ($first:ident $first_index:tt $(, $name:ident $index:tt)*);)*) => {$(
+        impl<$first: Serializable $(, $name: Serializable)*> Serializable for ($first, $($name,)*) {
+            fn streaming_serialize(&mut self, writer: &mut impl Write) -> Result<(), std::io::Error> {
+                writer.write_all(b"[")?;
+                self.$first_index.streaming_serialize(writer)?;
+                $(
+                    writer.write_all(b",")?;
+                    self.$index.streaming_serialize(writer)?;
+                )*
+                writer.write_all(b"]")
+            }
+        }
+
+        impl<$first: Deserializable $(, $name: Deserializable)*> Deserializable for ($first, $($name,)*) {
+            streaming_via_tokenizer!();
+
+            fn tokenized_deserialize<R: BufRead>(
+                reader: &mut Tokenizer<R>,
+            ) -> Result<Self, DeserializationError> {
+                let mut array = FixedArray::open(reader, $len)?;
+                let tuple = (
+                    {
+                        array.element(reader)?;
+                        $first::tokenized_deserialize(reader)?
+                    },
+                    $({
+                        array.element(reader)?;
+                        $name::tokenized_deserialize(reader)?
+                    },)*
+                );
+                array.close(reader)?;
+                Ok(tuple)
+            }
+        }
+    )*};
+}
+
+tuple_impls! {
+    1: (A 0);
+    2: (A 0, B 1);
+    3: (A 0, B 1, C 2);
+    4: (A 0, B 1, C 2, D 3);
+    5: (A 0, B 1, C 2, D 3, E 4);
+    6: (A 0, B 1, C 2, D 3, E 4, F 5);
+    7: (A 0, B 1, C 2, D 3, E 4, F 5, G 6);
+    8: (A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7);
+}
@@ -0,0 +1,46 @@
+// Helpers shared by the integration tests, each of which only uses some of them
+#![allow(dead_code)]
+
+use haversine_calculator::{generate::CoordPairGen, parser::Format, CoordPair};
+
+/// Compares through the binary format so every f64 has to match bit for bit
+pub fn bits(pairs: Vec<CoordPair>) -> Vec<u8> {
+    let mut out = vec![];
+    Format::Binary
+        .write_pairs(pairs.into_iter(), &mut out)
+        .unwrap();
+    out
+}
+
+/// Every coordinate's bits, in row order
+pub fn coord_bits(pairs: Vec<CoordPair>) -> Vec<u64> {
+    bits(pairs)[8..]
+        .chunks_exact(8)
+        .map(|bytes| u64::from_le_bytes(bytes.try_into().unwrap()))
+        .collect()
+}
+
+pub fn json(pairs: impl Iterator<Item = CoordPair>) -> Vec<u8> {
+    let mut out = vec![];
+    Format::Json.write_pairs(pairs, &mut out).unwrap();
+    out
+}
+
+/// `count` clustered pairs from `seed`, as a compact JSON array
+pub fn generated(seed: u64, count: usize) -> Vec<u8> {
+    written(Format::Json, seed, count)
+}
+
+/// `generated` as a string, for the parsers that take one
+pub fn compact(seed: u64, count: usize) -> String {
+    String::from_utf8(generated(seed, count)).unwrap()
+}
+
+/// `count` clustered pairs from `seed`, in `format`
+pub fn written(format: Format, seed: u64, count: usize) -> Vec<u8> {
+    let mut out = vec![];
+    format
+        .write_pairs(CoordPairGen::from_seed(seed, true, count), &mut out)
+        .unwrap();
+    out
+}
@@ -0,0 +1,113 @@
+use haversine_calculator::parser::{
+    deserialize, serialize, Deserializable, ErrorKind, Serializable,
+};
+
+#[derive(Debug, PartialEq, Serializable, Deserializable)]
+struct Point {
+    lat: f64,
+    lon: f64,
+}
+
+#[derive(Debug, PartialEq, Serializable, Deserializable)]
+struct Stop {
+    #[json(rename = "stop_id")]
+    id: u64,
+    offset: i32,
+    name: String,
+    active: bool,
+    location: Point,
+    #[json(rename = "with \"quotes\"")]
+    quoted: u8,
+}
+
+fn stop() -> Stop {
+    Stop {
+        id: 18_446_744_073_709_551_615,
+        offset: -12,
+        name: "Caf\u{e9} \"Central\"\\\n\t\u{1}\u{1F600}".to_string(),
+        active: true,
+        location: Point {
+            lat: 45.815,
+            lon: 15.9819,
+        },
+        quoted: 7,
+    }
+}
+
+fn to_json(value: &mut impl Serializable) -> String {
+    let mut out = vec![];
+    serialize(value, &mut out).unwrap();
+    String::from_utf8(out).unwrap()
+}
+
+#[test]
+fn writes_fields_in_order_with_renames() {
+    assert_eq!(
+        to_json(&mut stop()),
+        r#"{"stop_id":18446744073709551615,"offset":-12,"name":"Café \"Central\"\\\n\t\u0001😀","active":true,"location":{"lat":45.815,"lon":15.9819},"with \"quotes\"":7}"#
+    );
+}
+
+#[test]
+fn round_trips() {
+    let json = to_json(&mut stop());
+    let read: Stop = deserialize(&mut json.as_bytes()).unwrap();
+    assert_eq!(read, stop());
+}
+
+#[test]
+fn reads_any_key_order_layout_and_unknown_members() {
+    let json = r#" {
+        "location" : { "lon": 15.9819, "lat": 4.5815e1, "alt": [1, {"x": "}]"}] },
+        "extra": {"nested": ["\"", "\\"]},
+        "active": false, "name": "a\/b \u00e9 \ud83d\ude00", "offset": 0,
+        "with \"quotes\"": 7, "stop_id": 1, "tail": null
+    } "#;
+    let read: Stop = deserialize(&mut json.as_bytes()).unwrap();
+    assert_eq!(
+        read,
+        Stop {
+            id: 1,
+            offset: 0,
+            name: "a/b é 😀".to_string(),
+            active: false,
+            location: Point {
+                lat: 45.815,
+                lon: 15.9819
+            },
+            quoted: 7,
+        }
+    );
+}
+
+#[test]
+fn reads_arrays_of_derived_structs() {
+    let json = r#"[{"lat":1,"lon":2}, {"lon":4,"lat":3} ]"#;
+    let points: Vec<Point> = deserialize(&mut json.as_bytes()).unwrap();
+    assert_eq!(
+        points,
+        vec![Point { lat: 1.0, lon: 2.0 }, Point { lat: 3.0, lon: 4.0 }]
+    );
+}
+
+#[test]
+fn reports_errors_where_they_happen() {
+    let err = Point::streaming_deserialize(&mut &b"{\"lat\":1}"[..]).unwrap_err();
+    assert_eq!(err.kind, ErrorKind::MissingMember("lon".to_string()));
+
+    let err = Point::streaming_deserialize(&mut &b"{\"lat\":1,\n \"lon\":x}"[..]).unwrap_err();
+    assert_eq!(err.kind, ErrorKind::BadNumber);
+    assert_eq!((err.position.line, err.position.column), (2, 8));
+
+    let err = Stop::streaming_deserialize(&mut &b"{\"offset\":1.5"[..]).unwrap_err();
+    assert_eq!(err.kind, ErrorKind::BadNumber);
+
+    let err = Stop::streaming_deserialize(&mut &b"{\"name\":\"abc"[..]).unwrap_err();
+    assert_eq!(err.kind, ErrorKind::TruncatedInput);
+
+    let err = Point::streaming_deserialize(&mut &b"{\"lat\":1 \"lon\":2}"[..]).unwrap_err();
+    assert_eq!(err.kind, ErrorKind::UnexpectedByte(b'"'));
+
+    let err = Stop::streaming_deserialize(&mut &b"{\"name\":\"\\x\"}"[..]).unwrap_err();
+    assert_eq!(err.kind, ErrorKind::UnexpectedByte(b'x'));
+}
@@ -0,0 +1,156 @@
+use haversine_calculator::{float::parse_f64, rng::Xoshiro256StarStar};
+use rand::{RngCore, SeedableRng};
+
+fn same(a: Option<f64>, b: Option<f64>) -> bool {
+    match (a, b) {
+        (Some(a), Some(b)) if a.is_nan() => {
+            b.is_nan() && a.is_sign_negative() == b.is_sign_negative()
+        }
+        (Some(a), Some(b)) => a.to_bits() == b.to_bits(),
+        (None, None) => true,
+        _ => false,
+    }
+}
+
+fn check(s: &str) {
+    let expected = s.parse::<f64>().ok();
+    let got = parse_f64(s.as_bytes());
+    assert!(
+        same(got, expected),
+        "{s:?}: got {got:?}, expected {expected:?}"
+    );
+}
+
+#[test]
+fn edge_cases() {
+    for s in [
+        "0", "-0", "+0", "0.0", "00000", ".5", "5.", "-.5", ".", "", "-", "+", "e5", "1e", "1e+",
+        "1e-", "1.5e", "1.e5", "1e5x", " 1", "1 ", "1_0", "0x10", "--1", "+-1", "inf", "-inf",
+        "Infinity", "INF", "infinit", "nan", "NaN", "-nan", "nan(1)",
+        "1.7976931348623157e308", "1.7976931348623158e308", "1.7976931348623159e308",
+        "179769313486231580793728971405303415079934132710037826936173778980444968292764750946649017977587207096330286416692887910946555547851940402630657488671505820681908902000708383676273854845817711531764475730270069855571366959622842914819860834936475292719074168444365510704342711559699508093042880177904174497791.9999999",
+        "179769313486231580793728971405303415079934132710037826936173778980444968292764750946649017977587207096330286416692887910946555547851940402630657488671505820681908902000708383676273854845817711531764475730270069855571366959622842914819860834936475292719074168444365510704342711559699508093042880177904174497792",
+        "2.2250738585072011e-308", "2.2250738585072012e-308", "2.2250738585072014e-308",
+        "4.9406564584124654e-324", "2.4703282292062327e-324", "2.4703282292062328e-324",
+        "2.47032822920623272088e-324", "1e-400", "1e400", "1e-99999999999", "1e99999999999",
+        "0e99999999999", "9007199254740993", "9007199254740992.5", "9007199254740993.0000000001",
+        "1.00000000000000011102230246251565404236316680908203125",
+        "1.00000000000000011102230246251565404236316680908203124",
+        "1.00000000000000011102230246251565404236316680908203126",
+        "123456789012345678901234567890", "0.000000000000000000000000000001234567890123456789012",
+        "7.3177701707893310e+15", "1e23", "8.98846567431158e307", "3.0e-15", "1.5e30", "1e37",
+        "1e38", "12345678901234567890e-5",
+    ] {
+        check(s);
+    }
+}
+
+#[test]
+fn random_floats_in_every_spelling() {
+    let mut rng = Xoshiro256StarStar::seed_from_u64(39);
+    let mut ryu = ryu::Buffer::new();
+    for _ in 0..500_000 {
+        let val = f64::from_bits(rng.next_u64());
+        check(ryu.format(val));
+        check(&format!("{:e}", val));
+        check(&format!("{:.20e}", val));
+    }
+    // Values like the generator's coordinates
+    for _ in 0..500_000 {
+        let val = (rng.next_u64() >> 11) as f64 / (1u64 << 53) as f64 * 360.0 - 180.0;
+        check(ryu.format(val));
+        check(&format!("{}", val));
+    }
+}
+
+#[test]
+fn random_digit_strings() {
+    let mut rng = Xoshiro256StarStar::seed_from_u64(3939);
+    let mut s = String::new();
+    for _ in 0..1_000_000 {
+        s.clear();
+        let r = rng.next_u64();
+        if r & 1 != 0 {
+            s.push('-');
+        }
+        let len = 1 + (r >> 8) as usize % 40;
+        let point = (r >> 16) as usize % (len + 1);
+        for i in 0..len {
+            if i == point && i != 0 {
+                s.push('.');
+            }
+            s.push((b'0' + (rng.next_u64() % 10) as u8) as char);
+        }
+        if r & 2 != 0 {
+            let exp = (r >> 24) as i64 % 700 - 350;
+            s.push_str(&format!("e{exp}"));
+        }
+        check(&s);
+    }
+}
+
+/// Exact decimal digits of a finite float and the power of ten of the last digit
+fn exact_decimal(val: f64) -> (Vec<u8>, i64) {
+    let s = format!("{:.1100e}", val);
+    let (mantissa, exp) = s.split_once('e').unwrap();
+    let digits: Vec<u8> = mantissa
+        .bytes()
+        .filter(u8::is_ascii_digit)
+        .map(|b| b - b'0')
+        .collect();
+    let exp = exp.parse::<i64>().unwrap() - (digits.len() as i64 - 1);
+    (digits, exp)
+}
+
+/// Decimal string of the exact midpoint between two adjacent floats
+fn midpoint(a: f64, b: f64) -> String {
+    let (mut a, a_exp) = exact_decimal(a);
+    let (mut b, b_exp) = exact_decimal(b);
+    // Align the last digits, then add
+    let exp = a_exp.min(b_exp);
+    a.extend(std::iter::repeat_n(0, (a_exp - exp) as usize));
+    b.extend(std::iter::repeat_n(0, (b_exp - exp) as usize));
+    let len = a.len().max(b.len()) + 1;
+    let mut sum = vec![0u8; len];
+    let mut carry = 0;
+    for i in 0..len {
+        let da = if i < a.len() { a[a.len() - 1 - i] } else { 0 };
+        let db = if i < b.len() { b[b.len() - 1 - i] } else { 0 };
+        let d = da + db + carry;
+        sum[len - 1 - i] = d % 10;
+        carry = d / 10;
+    }
+    // Halve, the sum is odd in the last bit so this ends with a 5
+    let mut half = Vec::with_capacity(len + 1);
+    let mut rem = 0;
+    for d in sum.into_iter().chain([0]) {
+        let cur = rem * 10 + d;
+        half.push(b'0' + cur / 2);
+        rem = cur % 2;
+    }
+    format!("{}e{}", String::from_utf8(half).unwrap(), exp - 1)
+}
+
+#[test]
+fn halfway_cases() {
+    let mut rng = Xoshiro256StarStar::seed_from_u64(393939);
+    for i in 0..20_000 {
+        let bits = match i % 4 {
+            // Subnormals and the normal boundary
+            0 => rng.next_u64() % (1 << 53),
+            _ => rng.next_u64() % 0x7FEF_FFFF_FFFF_FFFF,
+        };
+        let mid = midpoint(f64::from_bits(bits), f64::from_bits(bits + 1));
+        check(&mid);
+        // Just above and just below
+        let (digits, exp) = mid.split_once('e').unwrap();
+        check(&format!("{digits}0000000001e{exp}"));
+        let trimmed = digits.trim_end_matches('5');
+        check(&format!("{trimmed}4999999999e{exp}"));
+        check(&format!(
+            "{}e{}",
+            &digits[..digits.len().min(20)],
+            exp.parse::<i64>().unwrap() + (digits.len() as i64 - 20).max(0)
+        ));
+    }
+}
@@ -0,0 +1,113 @@
+use haversine_calculator::{
+    generate::CoordPairGen,
+    parser::{deserialize_binary, deserialize_csv, ErrorKind, Format, BINARY_MAGIC},
+    CoordPair,
+};
+
+mod common;
+use common::coord_bits;
+
+/// Generated pairs with a few awkward values up front
+fn pairs() -> Vec<CoordPair> {
+    let awkward = [
+        [-0.0, 180.0, -90.0, -180.0],
+        [1e-300, -5e-324, 89.99999999999999, 0.1 + 0.2],
+        [
+            f64::MIN_POSITIVE,
+            1.0 / 3.0,
+            -45.123456789012345,
+            179.9999999999999,
+        ],
+    ];
+    awkward
+        .into_iter()
+        .map(|[lat0, lon0, lat1, lon1]| CoordPair::from(((lat0, lon0), (lat1, lon1))))
+        .chain(CoordPairGen::from_seed(27, true, 2_000))
+        .collect()
+}
+
+fn write(format: Format, pairs: Vec<CoordPair>) -> Vec<u8> {
+    let mut out = vec![];
+    format.write_pairs(pairs.into_iter(), &mut out).unwrap();
+    out
+}
+
+#[test]
+fn round_trips_bit_for_bit() {
+    let expected = coord_bits(pairs());
+    for format in [Format::Json, Format::Ndjson, Format::Csv, Format::Binary] {
+        let out = write(format, pairs());
+        let read = format.read_pairs(&mut &out[..]).unwrap();
+        assert_eq!(coord_bits(read), expected, "{format:?}");
+    }
+    for format in [Format::Ndjson, Format::Csv, Format::Binary] {
+        let out = write(format, vec![]);
+        assert!(format.read_pairs(&mut &out[..]).unwrap().is_empty());
+    }
+}
+
+#[test]
+fn csv_columns_follow_the_header() {
+    let input = b"lon1, lat1 ,lat0,lon0,note\r\n4,3,1,2,7\n\n8,7,5,6,0\n";
+    let read = deserialize_csv(&mut &input[..]).unwrap();
+    let expected = [((1.0, 2.0), (3.0, 4.0)), ((5.0, 6.0), (7.0, 8.0))];
+    assert_eq!(
+        coord_bits(read),
+        coord_bits(expected.into_iter().map(CoordPair::from).collect())
+    );
+}
+
+#[test]
+fn csv_rejects_bad_input() {
+    let err_at = |input: &[u8]| {
+        let err = deserialize_csv(&mut &input[..]).unwrap_err();
+        (err.kind, err.record, err.position.offset)
+    };
+    assert_eq!(
+        err_at(b"lat0,lon0,lat1\n1,2,3\n"),
+        (ErrorKind::MissingMember("lon1".into()), None, 0)
+    );
+    assert_eq!(
+        err_at(b""),
+        (ErrorKind::MissingMember("lat0".into()), None, 0)
+    );
+    assert_eq!(
+        err_at(b"lat0,lon0,lat1,lon1\n1,2,3,4\n1,2,3\n"),
+        (ErrorKind::TruncatedInput, Some(1), 28)
+    );
+    assert_eq!(
+        err_at(b"lat0,lon0,lat1,lon1\n1,2,3,4,5\n"),
+        (ErrorKind::UnexpectedByte(b','), Some(0), 20)
+    );
+    assert_eq!(
+        err_at(b"lat0,lon0,lat1,lon1\n1,2,3,4\n1,2x,3,4\n"),
+        (ErrorKind::BadNumber, Some(1), 30)
+    );
+}
+
+#[test]
+fn binary_rejects_bad_input() {
+    let good = write(Format::Binary, pairs().into_iter().take(3).collect());
+    let err_at = |input: &[u8]| {
+        let err = deserialize_binary(&mut &input[..]).unwrap_err();
+        (err.kind, err.record, err.position.offset)
+    };
+    let mut bad_magic = good.clone();
+    bad_magic[3] = b'X';
+    assert_eq!(
+        err_at(&bad_magic),
+        (ErrorKind::UnexpectedByte(b'X'), None, 3)
+    );
+    // A newer version
+    let mut version = good.clone();
+    version[7] = 2;
+    assert_eq!(err_at(&version), (ErrorKind::UnexpectedByte(2), None, 7));
+    assert_eq!(err_at(&good[..5]).0, ErrorKind::TruncatedInput);
+    // Cut inside the last pair
+    let len = BINARY_MAGIC.len() + 2 * 32;
+    assert_eq!(
+        err_at(&good[..len + 31]),
+        (ErrorKind::TruncatedInput, Some(2), len as u64)
+    );
+    assert_eq!(deserialize_binary(&mut &good[..len]).unwrap().len(), 2);
+}
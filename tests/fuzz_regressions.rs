@@ -0,0 +1,97 @@
+use std::{fs, path::PathBuf};
+
+use haversine_calculator::{
+    hvb::deserialize_hvb,
+    parser::{
+        deserialize, deserialize_binary, deserialize_csv, deserialize_ndjson, deserialize_parallel,
+        deserialize_single_pass, deserialize_tree, ArrayIter, DeserializationError, ParseOptions,
+    },
+    structural::deserialize_structural,
+    CoordPair,
+};
+
+// Malformed inputs for each fuzz target, one directory per target under fuzz/regressions. The single_pass,
+// streaming and ndjson ones are crashes the fuzzers found. The rest are written by hand as seeds, around the
+// edges each of those parsers has to handle. Every one has to be rejected rather than panic or be accepted
+
+fn check_corpus(
+    target: &str,
+    parse: impl Fn(&[u8]) -> Result<Vec<CoordPair>, DeserializationError>,
+) {
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("fuzz/regressions")
+        .join(target);
+    let mut checked = 0;
+    for entry in fs::read_dir(&dir).unwrap() {
+        let path = entry.unwrap().path();
+        let input = fs::read(&path).unwrap();
+        assert!(parse(&input).is_err(), "{} was accepted", path.display());
+        checked += 1;
+    }
+    assert!(checked > 0, "no inputs in {}", dir.display());
+}
+
+#[test]
+fn single_pass_regressions() {
+    check_corpus("single_pass", |input| match std::str::from_utf8(input) {
+        Ok(input) => deserialize_single_pass(input),
+        Err(_) => panic!("single_pass inputs have to be UTF-8"),
+    });
+}
+
+#[test]
+fn streaming_regressions() {
+    check_corpus("streaming", |mut input| deserialize(&mut input));
+}
+
+#[test]
+fn ndjson_regressions() {
+    check_corpus("ndjson", |mut input| deserialize_ndjson(&mut input));
+}
+
+#[test]
+fn tree_regressions() {
+    check_corpus("tree", deserialize_tree);
+}
+
+#[test]
+fn csv_regressions() {
+    check_corpus("csv", |mut input| deserialize_csv(&mut input));
+}
+
+#[test]
+fn binary_regressions() {
+    check_corpus("binary", |mut input| deserialize_binary(&mut input));
+}
+
+#[test]
+fn hvb_regressions() {
+    check_corpus("hvb", deserialize_hvb);
+}
+
+#[test]
+fn structural_regressions() {
+    check_corpus("structural", deserialize_structural);
+}
+
+#[test]
+fn parallel_regressions() {
+    // Like the fuzz target, the first byte picks the thread count
+    check_corpus("parallel", |input| {
+        let (&threads, input) = input.split_first().unwrap();
+        let input = std::str::from_utf8(input).unwrap();
+        assert!(deserialize_single_pass(input).is_err());
+        deserialize_parallel(input, threads as usize % 8 + 1)
+    });
+}
+
+#[test]
+fn recovering_regressions() {
+    // Skipping is fine, but each of these runs out of input, which still fails
+    check_corpus("recovering", |input| {
+        let mut pairs = ArrayIter::<_, CoordPair>::new(input, ParseOptions::default())
+            .recovering(4)
+            .with_max_record_len(64);
+        pairs.find_map(Result::err).map_or(Ok(vec![]), Err)
+    });
+}
@@ -0,0 +1,114 @@
+use haversine_calculator::{
+    generate::{ClusterShape, Concentration, CoordPairGen},
+    parser::Serializable,
+    region::{BoundingBox, Region},
+    rng::Xoshiro256StarStar,
+};
+use rand::{RngCore, SeedableRng};
+
+mod common;
+use common::coord_bits;
+
+fn first_pairs(gen: CoordPairGen, n: usize) -> Vec<String> {
+    gen.take(n)
+        .map(|mut pair| {
+            let mut out = vec![];
+            pair.streaming_serialize(&mut out).unwrap();
+            String::from_utf8(out).unwrap()
+        })
+        .collect()
+}
+
+#[test]
+fn xoshiro_matches_reference_vectors() {
+    // State [1, 2, 3, 4] is the vector used by the reference C implementation's test suite
+    let mut seed = [0u8; 32];
+    for (i, chunk) in seed.chunks_exact_mut(8).enumerate() {
+        chunk.copy_from_slice(&(i as u64 + 1).to_le_bytes());
+    }
+    let mut rng = Xoshiro256StarStar::from_seed(seed);
+    let got: Vec<u64> = (0..4).map(|_| rng.next_u64()).collect();
+    assert_eq!(got, [11520, 0, 1509978240, 1215971899390074240]);
+
+    let mut rng = Xoshiro256StarStar::seed_from_u64(0);
+    let got: Vec<u64> = (0..4).map(|_| rng.next_u64()).collect();
+    assert_eq!(
+        got,
+        [
+            0x99ec5f36cb75f2b4,
+            0xbf6e1f784956452a,
+            0x1a5f849d4933e6e0,
+            0x6aa594f1262d2d2c
+        ]
+    );
+}
+
+#[test]
+fn uniform_golden_output() {
+    assert_eq!(
+        first_pairs(CoordPairGen::from_seed(1212121212, false, 10), 4),
+        [
+            r#"{"lat0":-76.56843856407065,"lon0":48.69075953389117,"lon1":-96.88665015508047,"lat1":-70.27833751235795}"#,
+            r#"{"lat0":39.60741332279022,"lon0":6.580738544619095,"lon1":60.23673233777919,"lat1":69.09317562148803}"#,
+            r#"{"lat0":-52.59390877851121,"lon0":-39.10912719603067,"lon1":58.95273465331175,"lat1":43.66470466685328}"#,
+            r#"{"lat0":-8.302069695709548,"lon0":-1.4168779743185382,"lon1":12.865500766469552,"lat1":28.20566771189776}"#,
+        ]
+    );
+}
+
+#[test]
+fn clustered_golden_output() {
+    assert_eq!(
+        first_pairs(CoordPairGen::from_seed(1212121212, true, 10), 4),
+        [
+            r#"{"lat0":-72.22886057762284,"lon0":50.210057380826925,"lon1":62.597639001404,"lat1":-68.9982591762575}"#,
+            r#"{"lat0":-82.33087921871305,"lon0":39.66161905952129,"lon1":62.30120192154508,"lat1":-71.78432429705815}"#,
+            r#"{"lat0":-77.47805310011323,"lon0":48.363644326204785,"lon1":51.661023050929124,"lat1":-73.47809045933381}"#,
+            r#"{"lat0":-84.93787458850647,"lon0":70.94579749004092,"lon1":36.532324759047995,"lat1":-86.23616262085417}"#,
+        ]
+    );
+}
+
+#[test]
+fn generates_exact_count() {
+    for count in [0, 1, 999, 1000, 1001, 2500] {
+        assert_eq!(CoordPairGen::from_seed(7, true, count).count(), count);
+        assert_eq!(CoordPairGen::from_seed(7, false, count).count(), count);
+    }
+}
+
+#[test]
+fn cluster_bounds_wrap_at_the_antimeridian() {
+    let strip = BoundingBox {
+        min_lat: -10.0,
+        max_lat: 10.0,
+        min_lon: 179.0,
+        max_lon: -179.0,
+    };
+    for shape in [ClusterShape::Gaussian, ClusterShape::VonMisesFisher] {
+        let mut gen = CoordPairGen::from_seed(32, true, 1000)
+            .with_cluster_shape(shape, Concentration::new(1000.0).unwrap())
+            .with_region(Region::BoundingBox(strip));
+        let coords: Vec<f64> = coord_bits((&mut gen).collect())
+            .into_iter()
+            .map(f64::from_bits)
+            .collect();
+        let bounds = gen.clusters()[0].bounds;
+        // Points on both sides, without the box going the long way round
+        assert!(bounds.min_lon > bounds.max_lon, "{bounds:?}");
+        assert!(bounds.lon_span() <= 2.0, "{bounds:?}");
+        for point in coords.chunks_exact(2) {
+            assert!(bounds.contains(point[0], point[1]), "{point:?} {bounds:?}");
+        }
+    }
+}
+
+#[test]
+fn concentration_has_to_be_positive_and_finite() {
+    assert_eq!("2.5".parse(), Ok(Concentration::new(2.5).unwrap()));
+    for bad in ["0", "-1", "NaN", "inf", "-inf", "x", ""] {
+        assert!(bad.parse::<Concentration>().is_err(), "{bad}");
+    }
+    assert!(Concentration::new(-0.0).is_none());
+    assert!(Concentration::new(f64::INFINITY).is_none());
+}
@@ -0,0 +1,215 @@
+use haversine_calculator::{
+    generate::CoordPairGen,
+    hvb::{deserialize_hvb, hvb_count_for_len, write_hvb, Endian, HvbFile, Layout, HVB_HEADER_LEN},
+    parser::{ErrorKind, Format},
+    CoordPair,
+};
+
+mod common;
+use common::coord_bits;
+
+fn pairs(count: usize) -> Vec<CoordPair> {
+    CoordPairGen::from_seed(48, true, count).collect()
+}
+
+fn hvb(count: usize, layout: Layout, endian: Endian) -> Vec<u8> {
+    let mut out = vec![];
+    write_hvb(pairs(count).into_iter(), &mut out, layout, endian).unwrap();
+    out
+}
+
+/// Copies `bytes` into an allocation of u64s so the body is 8 byte aligned
+fn aligned(bytes: &[u8]) -> Vec<u64> {
+    let mut words = vec![0u64; bytes.len().div_ceil(8)];
+    for (word, chunk) in words.iter_mut().zip(bytes.chunks(8)) {
+        let mut buf = [0; 8];
+        buf[..chunk.len()].copy_from_slice(chunk);
+        *word = u64::from_ne_bytes(buf);
+    }
+    words
+}
+
+fn as_bytes(words: &[u64], len: usize) -> &[u8] {
+    // Any u64 can be looked at as bytes
+    unsafe { std::slice::from_raw_parts(words.as_ptr().cast(), len) }
+}
+
+#[test]
+fn round_trips_every_layout_and_byte_order() {
+    let expected = coord_bits(pairs(1_000));
+    for layout in [Layout::Rows, Layout::Columns] {
+        for endian in [Endian::Little, Endian::Big] {
+            let out = hvb(1_000, layout, endian);
+            assert_eq!(out.len(), HVB_HEADER_LEN + 1_000 * 32 + 8);
+            let file = HvbFile::new(&out).unwrap();
+            assert_eq!(
+                (file.layout, file.endian, file.len()),
+                (layout, endian, 1_000)
+            );
+            file.verify().unwrap();
+            assert_eq!(coord_bits(file.pairs().collect()), expected);
+            assert_eq!(coord_bits(vec![file.pair(999)]), expected[999 * 4..]);
+            assert_eq!(coord_bits(deserialize_hvb(&out).unwrap()), expected);
+        }
+    }
+    let empty = hvb(0, Layout::Rows, Endian::Little);
+    assert!(deserialize_hvb(&empty).unwrap().is_empty());
+}
+
+#[test]
+fn reads_values_in_place() {
+    for layout in [Layout::Rows, Layout::Columns] {
+        let out = hvb(100, layout, Endian::NATIVE);
+        let words = aligned(&out);
+        let file = HvbFile::new(as_bytes(&words, out.len())).unwrap();
+        let values = file.values().unwrap();
+        assert_eq!(values.len(), 400);
+        let first = coord_bits(pairs(1));
+        let second = match layout {
+            Layout::Rows => values[1],
+            Layout::Columns => values[100],
+        };
+        assert_eq!(
+            (values[0].to_bits(), second.to_bits()),
+            (first[0], first[1])
+        );
+    }
+
+    let other = match Endian::NATIVE {
+        Endian::Little => Endian::Big,
+        Endian::Big => Endian::Little,
+    };
+    let out = hvb(10, Layout::Rows, other);
+    let words = aligned(&out);
+    assert!(HvbFile::new(as_bytes(&words, out.len()))
+        .unwrap()
+        .values()
+        .is_none());
+}
+
+#[test]
+fn goes_through_format() {
+    for format in [Format::Hvb, Format::HvbColumns] {
+        let mut out = vec![];
+        format
+            .write_pairs(pairs(500).into_iter(), &mut out)
+            .unwrap();
+        assert_eq!(Format::detect(&out), Some(Format::Hvb));
+        assert_eq!(Format::detect(&out[..4]), Some(Format::Hvb));
+        let read = format.read_pairs(&mut &out[..]).unwrap();
+        assert_eq!(coord_bits(read), coord_bits(pairs(500)));
+    }
+}
+
+#[test]
+fn rejects_bad_headers() {
+    let good = hvb(10, Layout::Rows, Endian::Little);
+    let err_at = |edit: &dyn Fn(&mut Vec<u8>)| {
+        let mut out = good.clone();
+        edit(&mut out);
+        let err = HvbFile::new(&out).unwrap_err();
+        (err.kind, err.position.offset)
+    };
+
+    assert_eq!(
+        err_at(&|out| out[1] = b'X'),
+        (ErrorKind::UnexpectedByte(b'X'), 1)
+    );
+    assert_eq!(
+        err_at(&|out| out.truncate(10)),
+        (ErrorKind::TruncatedInput, 10)
+    );
+    assert_eq!(err_at(&|out| out[4] = 2), (ErrorKind::UnexpectedByte(2), 4));
+    assert_eq!(err_at(&|out| out[6] = 3), (ErrorKind::UnexpectedByte(3), 6));
+    assert_eq!(err_at(&|out| out[8] = 2), (ErrorKind::UnexpectedByte(2), 8));
+    assert_eq!(
+        err_at(&|out| out[12] = 1),
+        (ErrorKind::UnexpectedByte(1), 12)
+    );
+    // A pair short, and a byte too many
+    assert_eq!(
+        err_at(&|out| out.truncate(out.len() - 32)),
+        (ErrorKind::TruncatedInput, good.len() as u64 - 32)
+    );
+    assert_eq!(
+        err_at(&|out| out.push(7)),
+        (ErrorKind::UnexpectedByte(7), good.len() as u64)
+    );
+    // A count too large to ever fit
+    assert_eq!(
+        err_at(&|out| out[16..24].copy_from_slice(&u64::MAX.to_le_bytes())),
+        (ErrorKind::TruncatedInput, good.len() as u64)
+    );
+}
+
+#[test]
+fn checks_the_checksum() {
+    let mut out = hvb(10, Layout::Columns, Endian::Big);
+    out[HVB_HEADER_LEN + 100] ^= 1;
+    let file = HvbFile::new(&out).unwrap();
+    let err = file.verify().unwrap_err();
+    assert_eq!(err.kind, ErrorKind::BadChecksum);
+    assert_eq!(err.position.offset, out.len() as u64 - 8);
+    assert_eq!(
+        deserialize_hvb(&out).unwrap_err().kind,
+        ErrorKind::BadChecksum
+    );
+}
+
+/// Passes `pairs` through, claiming there are exactly `hint` of them
+struct Hinted<I> {
+    pairs: I,
+    hint: usize,
+}
+
+impl<I: Iterator<Item = CoordPair>> Iterator for Hinted<I> {
+    type Item = CoordPair;
+
+    fn next(&mut self) -> Option<CoordPair> {
+        self.pairs.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.hint, Some(self.hint))
+    }
+}
+
+#[test]
+fn counts_pairs_it_cant_see_ahead() {
+    // No exact size, so they're gathered before the header is written
+    let expected = coord_bits(pairs(100));
+    for layout in [Layout::Rows, Layout::Columns] {
+        let mut out = vec![];
+        let filtered = pairs(100).into_iter().filter(|_| true);
+        write_hvb(filtered, &mut out, layout, Endian::Little).unwrap();
+        assert_eq!(coord_bits(deserialize_hvb(&out).unwrap()), expected);
+    }
+
+    // An exact size goes straight into the header, and has to be right
+    assert_eq!(
+        CoordPairGen::from_seed(48, true, 5).size_hint(),
+        (5, Some(5))
+    );
+    for (hint, layout) in [
+        (99, Layout::Rows),
+        (101, Layout::Rows),
+        (99, Layout::Columns),
+    ] {
+        let pairs = Hinted {
+            pairs: pairs(100).into_iter(),
+            hint,
+        };
+        let err = write_hvb(pairs, &mut vec![], layout, Endian::Little).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+    }
+}
+
+#[test]
+fn sizes_files_to_a_target() {
+    for target in [0, 32, 33, 64, 1_000, 104_000] {
+        let count = hvb_count_for_len(target);
+        let len = hvb(count, Layout::Rows, Endian::Little).len() as u64;
+        assert!(len >= target, "{target}");
+        assert!(count == 0 || len - 32 < target, "{target}");
+    }
+}
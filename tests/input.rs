@@ -0,0 +1,55 @@
+#![cfg(target_os = "linux")]
+
+use std::{
+    fs::{self, File},
+    path::PathBuf,
+};
+
+use haversine_calculator::{
+    input::{Advice, Mmap},
+    parser::deserialize_single_pass,
+};
+
+mod common;
+use common::{bits, compact};
+
+/// Writes `contents` to a file of its own under the temp directory
+fn temp_file(name: &str, contents: &[u8]) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("hav-input-{}-{name}", std::process::id()));
+    fs::write(&path, contents).unwrap();
+    path
+}
+
+#[test]
+fn mapped_input_parses_like_read_input() {
+    let path = temp_file("pairs.json", compact(37, 2_000).as_bytes());
+    let map = Mmap::open(&File::open(&path).unwrap()).unwrap();
+    for advice in [Advice::Sequential, Advice::Hugepage] {
+        // Best-effort, but the mapping has to stay readable either way
+        let _ = map.advise(advice);
+    }
+    let read = fs::read_to_string(&path).unwrap();
+    assert_eq!(&map[..], read.as_bytes());
+    assert_eq!(
+        bits(deserialize_single_pass(std::str::from_utf8(&map).unwrap()).unwrap()),
+        bits(deserialize_single_pass(&read).unwrap())
+    );
+    drop(map);
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn maps_an_empty_file() {
+    let path = temp_file("empty.json", b"");
+    let map = Mmap::open(&File::open(&path).unwrap()).unwrap();
+    map.advise(Advice::Sequential).unwrap();
+    assert!(map.is_empty());
+    let mapped = deserialize_single_pass(std::str::from_utf8(&map).unwrap()).unwrap_err();
+    let read = deserialize_single_pass(&fs::read_to_string(&path).unwrap()).unwrap_err();
+    assert_eq!(
+        (mapped.kind, mapped.position.offset),
+        (read.kind, read.position.offset)
+    );
+    drop(map);
+    fs::remove_file(path).unwrap();
+}
@@ -0,0 +1,114 @@
+use haversine_calculator::{
+    json::{parse_json, JsonValue},
+    parser::{deserialize_tree, ErrorKind},
+};
+
+fn string(input: &str) -> String {
+    parse_json(input.as_bytes())
+        .unwrap()
+        .as_str()
+        .unwrap()
+        .to_string()
+}
+
+fn error_at(input: &[u8]) -> (ErrorKind, u64) {
+    let err = parse_json(input).unwrap_err();
+    (err.kind, err.position.offset)
+}
+
+#[test]
+fn unescapes_strings() {
+    assert_eq!(
+        string(r#""a\"b\\c\/d\b\f\n\r\t""#),
+        "a\"b\\c/d\u{8}\u{c}\n\r\t"
+    );
+    assert_eq!(string(r#""\u0041\u00e9\u20AC""#), "Aé€");
+    assert_eq!(string("\"caf\u{e9} \u{1F600}\""), "café 😀");
+    assert_eq!(error_at(br#""a\x""#), (ErrorKind::UnexpectedByte(b'x'), 3));
+    assert_eq!(error_at(b"\"a\nb\""), (ErrorKind::UnexpectedByte(b'\n'), 2));
+    assert_eq!(error_at(br#""abc"#), (ErrorKind::TruncatedInput, 4));
+    assert_eq!(error_at(b"\"\xFF\""), (ErrorKind::UnexpectedByte(0xFF), 1));
+}
+
+#[test]
+fn pairs_surrogates() {
+    assert_eq!(string(r#""\uD83D\uDE00""#), "😀");
+    assert_eq!(string(r#""\ud834\udd1e!""#), "\u{1D11E}!");
+    // A high surrogate needs a low one right after it, and a low one can't come first
+    assert_eq!(
+        error_at(br#""\uD83D""#),
+        (ErrorKind::UnexpectedByte(b'"'), 7)
+    );
+    assert_eq!(
+        error_at(br#""\uD83Dx""#),
+        (ErrorKind::UnexpectedByte(b'x'), 7)
+    );
+    assert_eq!(
+        error_at(br#""\uD83D\u0041""#),
+        (ErrorKind::UnexpectedByte(b'0'), 9)
+    );
+    assert_eq!(
+        error_at(br#""\uDE00\uD83D""#),
+        (ErrorKind::UnexpectedByte(b'D'), 3)
+    );
+}
+
+#[test]
+fn skips_a_leading_bom() {
+    let value = parse_json(b"\xEF\xBB\xBF [1, {\"a\": null}]").unwrap();
+    assert_eq!(value.pointer("/1/a"), Some(&JsonValue::Null));
+    // Only at the very start
+    assert!(parse_json(b" \xEF\xBB\xBF[]").is_err());
+    assert!(parse_json(b"[]\xEF\xBB\xBF").is_err());
+}
+
+#[test]
+fn limits_nesting() {
+    let nested = |depth| format!("{}{}", "[".repeat(depth), "]".repeat(depth));
+    assert!(parse_json(nested(128).as_bytes()).is_ok());
+    let err = parse_json(nested(129).as_bytes()).unwrap_err();
+    assert_eq!(
+        (err.kind, err.position.offset),
+        (ErrorKind::UnexpectedByte(b'['), 128)
+    );
+    // Objects count too, and don't blow the stack far past the limit
+    let deep = "{\"a\":".repeat(100_000);
+    assert!(parse_json(deep.as_bytes()).is_err());
+}
+
+#[test]
+fn looks_up_pointers() {
+    let value = parse_json(br#"{"a/b": {"m~n": [10, {"": 20}]}, "c": 1, "c": 2}"#).unwrap();
+    assert_eq!(value.pointer(""), Some(&value));
+    assert_eq!(
+        value.pointer("/a~1b/m~0n/0"),
+        Some(&JsonValue::Number(10.0))
+    );
+    assert_eq!(
+        value.pointer("/a~1b/m~0n/1/"),
+        Some(&JsonValue::Number(20.0))
+    );
+    // `~01` is `~1` unescaped, not `/`
+    let tilde = parse_json(br#"{"~1": 1, "/": 2}"#).unwrap();
+    assert_eq!(tilde.pointer("/~01"), Some(&JsonValue::Number(1.0)));
+    assert_eq!(tilde.pointer("/~1"), Some(&JsonValue::Number(2.0)));
+    // The last of repeated keys
+    assert_eq!(value.pointer("/c"), Some(&JsonValue::Number(2.0)));
+    for missing in ["a~1b", "/a", "/a~1b/m~0n/2", "/a~1b/m~0n/x", "/c/0"] {
+        assert_eq!(value.pointer(missing), None, "{missing}");
+    }
+}
+
+#[test]
+fn locates_the_failing_pair() {
+    let record = r#"{"lat0":1,"lon0":2,"lat1":3}"#;
+    let good = r#"{"lat0":1,"lon0":2,"lat1":3,"lon1":4}"#;
+    let bare = format!("[{good}, {good},\n {record}]");
+    let envelope =
+        format!(r#"{{"note": [1, {{"pairs": 2}}], "pairs": [{good}, {good},  {record}]}}"#);
+    for input in [bare, envelope] {
+        let err = deserialize_tree(input.as_bytes()).unwrap_err();
+        assert_eq!(err.record, Some(2));
+        assert_eq!(err.position.offset, input.rfind(record).unwrap() as u64);
+    }
+}
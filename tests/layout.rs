@@ -0,0 +1,198 @@
+use haversine_calculator::{
+    generate::CoordPairGen,
+    messy::MessyJson,
+    parser::{deserialize, deserialize_parallel, deserialize_single_pass, deserialize_tree},
+    rng::Xoshiro256StarStar,
+    structural::deserialize_structural,
+};
+use rand::SeedableRng;
+
+mod common;
+use common::{bits, compact};
+
+/// Re-lays out compact JSON with `indent` per level, `newline` between lines and `colon` after keys
+fn pretty(compact: &str, indent: &str, newline: &str, colon: &str) -> String {
+    let mut out = String::with_capacity(compact.len() * 2);
+    let mut depth = 0;
+    for c in compact.chars() {
+        match c {
+            '[' | '{' => {
+                depth += 1;
+                out.push(c);
+                out.push_str(newline);
+                out.push_str(&indent.repeat(depth));
+            }
+            ']' | '}' => {
+                depth -= 1;
+                out.push_str(newline);
+                out.push_str(&indent.repeat(depth));
+                out.push(c);
+            }
+            ',' => {
+                out.push(c);
+                out.push_str(newline);
+                out.push_str(&indent.repeat(depth));
+            }
+            ':' => out.push_str(colon),
+            c => out.push(c),
+        }
+    }
+    out.push_str(newline);
+    out
+}
+
+fn assert_matches_streaming(input: &str, expected: &[u8]) {
+    let single_pass = bits(deserialize_single_pass(input).unwrap());
+    assert_eq!(
+        single_pass,
+        expected,
+        "{:?}",
+        &input[..input.len().min(200)]
+    );
+    assert_eq!(bits(deserialize_parallel(input, 4).unwrap()), expected);
+}
+
+#[test]
+fn reads_reformatted_files() {
+    let json = compact(44, 30_000);
+    let expected = bits(deserialize(&mut json.as_bytes()).unwrap());
+    for input in [
+        format!("{json}\n"),
+        format!("{json}\r\n"),
+        format!("\u{FEFF}{json}"),
+        format!(" \t\n{json} \n\n"),
+        pretty(&json, "  ", "\n", ": "),
+        pretty(&json, "\t", "\r\n", " : "),
+        pretty(&json, "", "", " :\n "),
+        json.replace(',', " , ").replace(':', ":   "),
+    ] {
+        // The streaming parser reads the same layouts, except for the BOM
+        let without_bom = input.trim_start_matches('\u{FEFF}');
+        assert_eq!(
+            bits(deserialize(&mut without_bom.as_bytes()).unwrap()),
+            expected
+        );
+        assert_matches_streaming(&input, &expected);
+    }
+}
+
+#[test]
+fn reads_messy_files() {
+    for seed in [1, 2, 3] {
+        let mut input = vec![];
+        MessyJson::new(Xoshiro256StarStar::seed_from_u64(seed), false)
+            .write_pairs(CoordPairGen::from_seed(seed, true, 30_000), &mut input)
+            .unwrap();
+        let expected = bits(deserialize(&mut &input[..]).unwrap());
+        assert_matches_streaming(std::str::from_utf8(&input).unwrap(), &expected);
+    }
+}
+
+/// Messy output with extra members of every kind of value, which the single-pass parser has to skip
+fn messy_with_extra_keys(seed: u64, count: usize) -> Vec<u8> {
+    let mut input = vec![];
+    MessyJson::new(Xoshiro256StarStar::seed_from_u64(seed), true)
+        .write_pairs(CoordPairGen::from_seed(seed, true, count), &mut input)
+        .unwrap();
+    input
+}
+
+#[test]
+fn skips_unknown_members() {
+    for seed in [1, 2, 3] {
+        let input = messy_with_extra_keys(seed, 30_000);
+        let expected = bits(deserialize(&mut &input[..]).unwrap());
+        assert_matches_streaming(std::str::from_utf8(&input).unwrap(), &expected);
+    }
+
+    let input = r#"[{"x": {"a": [1, "}]", {}], "b": null}, "lat0":1, "s":"a\"b,", "lon0":2,
+        "t" : true, "lat1":3, "e": [], "lon1":4, "n": -1.5e3}]"#;
+    let expected = bits(deserialize(&mut input.as_bytes()).unwrap());
+    assert_matches_streaming(input, &expected);
+    for input in [
+        r#"[{"x": {"a": [1, 2}, "lat0":1,"lon0":2,"lat1":3,"lon1":4}]"#,
+        r#"[{"x": "open,"lat0":1,"lon0":2,"lat1":3,"lon1":4}]"#,
+        r#"[{"x": ,"lat0":1,"lon0":2,"lat1":3,"lon1":4}]"#,
+        r#"[{"x": {} 1,"lat0":1,"lon0":2,"lat1":3,"lon1":4}]"#,
+    ] {
+        assert!(deserialize_single_pass(input).is_err(), "{input}");
+        assert!(deserialize_parallel(input, 4).is_err(), "{input}");
+    }
+}
+
+#[test]
+fn every_parser_round_trips_extra_keys() {
+    for seed in [4, 5] {
+        // The layout has a random stream of its own, the values are the generator's
+        let expected = bits(CoordPairGen::from_seed(seed, true, 20_000).collect());
+        let input = messy_with_extra_keys(seed, 20_000);
+        let text = std::str::from_utf8(&input).unwrap();
+        let parsers = [
+            ("streaming", deserialize(&mut &input[..])),
+            ("tree", deserialize_tree(&input)),
+            ("single pass", deserialize_single_pass(text)),
+            ("structural", deserialize_structural(&input)),
+            ("parallel", deserialize_parallel(text, 4)),
+        ];
+        for (name, pairs) in parsers {
+            assert_eq!(bits(pairs.unwrap()), expected, "{name} {seed}");
+        }
+    }
+}
+
+#[test]
+fn parallel_cuts_can_land_in_skipped_values() {
+    // Most of each record is a member full of `}, {` that isn't a record boundary
+    let noise = format!(
+        "[{}]",
+        [r#"{"a":1}, {"b":"}, {"}, {"c":[{}, {}]}"#; 20].join(", ")
+    );
+    let records: Vec<String> = (0..5_000)
+        .map(|i| format!(r#"{{"x":{noise},"lat0":{i},"lon0":2,"lat1":3,"lon1":4}}"#))
+        .collect();
+    let input = format!("[{}]", records.join(", "));
+    let expected = bits(deserialize(&mut input.as_bytes()).unwrap());
+    for threads in [2, 3, 8] {
+        assert_eq!(
+            bits(deserialize_parallel(&input, threads).unwrap()),
+            expected
+        );
+    }
+
+    // Errors past a bad cut still have their index among all the records
+    let broken = input.replacen(r#""lat0":4000,"#, r#""lat0":4000x,"#, 1);
+    let err = deserialize_parallel(&broken, 8).unwrap_err();
+    assert_eq!(err.record, Some(4_000));
+    assert_eq!(err.position.offset, broken.find("4000x").unwrap() as u64);
+}
+
+#[test]
+fn reads_empty_arrays() {
+    for input in ["[]", " [ \r\n ] \n", "\u{FEFF}[]"] {
+        assert!(deserialize_single_pass(input).unwrap().is_empty());
+        assert!(deserialize_parallel(input, 4).unwrap().is_empty());
+    }
+}
+
+#[test]
+fn rejects_malformed_layouts() {
+    for input in [
+        "",
+        "[",
+        "]",
+        "[{}]",
+        r#"[{"lat0":1,"lon0":2,"lat1":3,"lon1":4},]"#,
+        r#"[{"lat0":1,"lon0":2,"lat1":3,"lon1":4}}]"#,
+        r#"[{"lat0":1,"lon0":2,"lat1":3,"lon1":4} {"lat0":1,"lon0":2,"lat1":3,"lon1":4}]"#,
+        r#"[{"lat0":1,"lon0":2,"lat1":3,"lon1":4}]x"#,
+        r#"[{"lat0":1 "lon0":2,"lat1":3,"lon1":4}]"#,
+        r#"[{"lat0":1,"lon0":2,"lat1":3,"lon1":}]"#,
+        r#"[{"lat0":1,"lon0":2,"lat1":3,"lon1":"4"}]"#,
+        r#"[{"lat0":1,"lon0":2,"lat1":3,"lon1":4 5}]"#,
+        r#"[{lat0:1,"lon0":2,"lat1":3,"lon1":4}]"#,
+        r#"[{"lat0:1,"lon0":2,"lat1":3,"lon1":4}]"#,
+    ] {
+        assert!(deserialize_single_pass(input).is_err(), "{input}");
+        assert!(deserialize_parallel(input, 4).is_err(), "{input}");
+    }
+}
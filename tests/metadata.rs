@@ -0,0 +1,70 @@
+use std::io::Write;
+
+use haversine_calculator::{
+    generate::{ClusterShape, Concentration, CoordPairGen},
+    metadata::{Checksum, ChecksumWriter, GenerationMetadata},
+    parser::Format,
+};
+
+/// A clustered file and the metadata its generation would write next to it
+fn generated() -> (Vec<u8>, GenerationMetadata) {
+    let mut gen = CoordPairGen::from_seed(32, true, 2_500)
+        .with_cluster_shape(ClusterShape::Gaussian, Concentration::new(50.0).unwrap());
+    let mut data = vec![];
+    let mut writer = ChecksumWriter::new(&mut data);
+    Format::Json.write_pairs(&mut gen, &mut writer).unwrap();
+    writer.flush().unwrap();
+    let (bytes, written) = (writer.bytes(), writer.checksum());
+    assert_eq!((bytes, written), (data.len() as u64, checksum(&data)));
+    let mut metadata = GenerationMetadata::new(32, 2_500, "gaussian");
+    metadata.param("format", "json");
+    metadata.param("concentration", Concentration::new(50.0).unwrap());
+    metadata.record_clusters(gen.clusters());
+    metadata.bytes = bytes;
+    metadata.checksum = written;
+    (data, metadata)
+}
+
+fn checksum(bytes: &[u8]) -> u64 {
+    let mut checksum = Checksum::default();
+    checksum.update(bytes);
+    checksum.finish()
+}
+
+#[test]
+fn round_trips() {
+    let (_, metadata) = generated();
+    assert_eq!(metadata.clusters.len(), 3);
+    let mut out = vec![];
+    metadata.write(&mut out).unwrap();
+    assert_eq!(GenerationMetadata::read(&mut &out[..]).unwrap(), metadata);
+
+    let text = String::from_utf8(out).unwrap();
+    let without_checksum: String = text
+        .lines()
+        .filter(|line| !line.starts_with("checksum"))
+        .collect::<Vec<_>>()
+        .join("\n");
+    assert!(GenerationMetadata::read(&mut without_checksum.as_bytes()).is_err());
+    let unknown = format!("{text}colour = blue\n");
+    assert!(GenerationMetadata::read(&mut unknown.as_bytes()).is_err());
+}
+
+#[test]
+fn verify_catches_changes() {
+    let (data, metadata) = generated();
+    metadata.verify(data.len() as u64, checksum(&data)).unwrap();
+
+    let truncated = &data[..data.len() - 1];
+    let err = metadata
+        .verify(truncated.len() as u64, checksum(truncated))
+        .unwrap_err();
+    assert!(err.contains("truncated"), "{err}");
+
+    let mut modified = data.clone();
+    modified[data.len() / 2] ^= 1;
+    let err = metadata
+        .verify(modified.len() as u64, checksum(&modified))
+        .unwrap_err();
+    assert!(err.contains("checksum"), "{err}");
+}
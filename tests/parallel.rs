@@ -0,0 +1,55 @@
+use haversine_calculator::parser::{deserialize_parallel, deserialize_single_pass};
+
+mod common;
+use common::{bits, compact};
+
+#[test]
+fn matches_single_pass() {
+    let input = compact(40, 100_000);
+    let expected = bits(deserialize_single_pass(&input).unwrap());
+    for threads in [0, 1, 2, 3, 7, 64] {
+        assert_eq!(
+            bits(deserialize_parallel(&input, threads).unwrap()),
+            expected,
+            "{threads} threads"
+        );
+    }
+    let small = r#"[{"lat0":1,"lon0":2,"lat1":3,"lon1":4}]"#;
+    assert_eq!(
+        bits(deserialize_parallel(small, 8).unwrap()),
+        bits(deserialize_single_pass(small).unwrap())
+    );
+}
+
+#[test]
+fn reports_the_same_first_error() {
+    let input = compact(41, 100_000);
+    // Break records near the start, middle and end, and several at once so later chunks fail too
+    let len = input.len();
+    for offsets in [
+        vec![10],
+        vec![len / 2],
+        vec![len - 10],
+        vec![len / 3, len / 2, len * 9 / 10],
+    ] {
+        for corrupt in [b'x', b';', b'}'] {
+            let mut broken = input.clone().into_bytes();
+            for &offset in &offsets {
+                broken[offset] = corrupt;
+            }
+            let broken = String::from_utf8(broken).unwrap();
+            let expected = deserialize_single_pass(&broken).unwrap_err();
+            for threads in [2, 5, 16] {
+                let err = deserialize_parallel(&broken, threads).unwrap_err();
+                assert_eq!(err.to_string(), expected.to_string());
+                assert_eq!(err.record, expected.record);
+            }
+        }
+    }
+    for input in ["", "[", "[{}", "{}]"] {
+        assert_eq!(
+            deserialize_parallel(input, 4).unwrap_err().to_string(),
+            deserialize_single_pass(input).unwrap_err().to_string()
+        );
+    }
+}
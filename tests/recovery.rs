@@ -0,0 +1,181 @@
+use haversine_calculator::{
+    parser::{
+        deserialize_iter, deserialize_ndjson_recovering, ArrayIter, ErrorKind, ParseOptions,
+        Skipped,
+    },
+    CoordPair,
+};
+
+mod common;
+use common::generated;
+
+const INPUT: &str = r#"[{"lat0":1,"lon0":2,"lat1":3,"lon1":4},
+ {"lat0":1x,"lon0":2,"lat1":3,"lon1":4},
+ {"lat0":1,"lon0":2,"lat1":3,"lon1":4, "note": "],[{"},
+ {"lat0":1,"lon0":2,"lat1":3},
+ {"lat0":1,"lon0":2,"lat1":3,"lon1":4} {"lat0":9},
+ {"lat0":5,"lon0":6,"lat1":7,"lon1":8, "extra": [1, {"x": }]},
+ {"lat0":1,"lon0":2,"lat1":3,"lon1":4}]"#;
+
+fn offset_of(needle: &str) -> u64 {
+    offset_in(INPUT, needle)
+}
+
+fn offset_in(input: &str, needle: &str) -> u64 {
+    input.find(needle).unwrap() as u64
+}
+
+fn recover(input: &str, options: ParseOptions, max_diagnostics: usize) -> (usize, Skipped) {
+    let mut pairs =
+        ArrayIter::<_, CoordPair>::new(input.as_bytes(), options).recovering(max_diagnostics);
+    let read = (&mut pairs).collect::<Result<Vec<_>, _>>().unwrap();
+    (read.len(), pairs.into_skipped().unwrap())
+}
+
+#[test]
+fn skips_broken_records() {
+    let (read, skipped) = recover(INPUT, ParseOptions::default(), 10);
+    assert_eq!(read, 5);
+    assert_eq!(skipped.count, 3);
+    let found: Vec<_> = skipped
+        .diagnostics
+        .iter()
+        .map(|err| (err.kind.clone(), err.record, err.position.offset))
+        .collect();
+    assert_eq!(
+        found,
+        [
+            (ErrorKind::BadNumber, Some(1), offset_of("1x")),
+            (
+                ErrorKind::MissingMember("lon1".into()),
+                Some(3),
+                // Just past the record, where it turned out to be missing
+                offset_of(r#""lat1":3},"#) + 9
+            ),
+            (
+                ErrorKind::UnexpectedByte(b'{'),
+                Some(5),
+                offset_of(r#"{"lat0":9}"#)
+            ),
+        ]
+    );
+
+    // Without recovery the first one is the end of it
+    let err = deserialize_iter::<CoordPair>(INPUT.as_bytes())
+        .find_map(Result::err)
+        .unwrap();
+    assert_eq!((err.kind, err.record), (ErrorKind::BadNumber, Some(1)));
+}
+
+/// Six good records with the third broken by `corrupt`
+fn six_records(corrupt: impl Fn(&str) -> String) -> String {
+    let records: Vec<String> = (0..6)
+        .map(|i| {
+            let record = format!(r#"{{"lat0":{i},"lon0":2,"lat1":3,"lon1":4}}"#);
+            match i {
+                2 => corrupt(&record),
+                _ => record,
+            }
+        })
+        .collect();
+    format!("[{}]", records.join(", "))
+}
+
+#[test]
+fn resyncs_at_the_next_record() {
+    let missing_brace = six_records(|record| record.trim_end_matches('}').to_string());
+    let open_string = six_records(|record| record.replace(r#""lon1":4"#, r#""lon1":"4"#));
+    let stray_bracket = six_records(|record| record.replace(r#""lat0":2"#, r#""lat0":[2"#));
+    for input in [&missing_brace, &open_string, &stray_bracket] {
+        let (read, skipped) = recover(input, ParseOptions::default(), 10);
+        assert_eq!((read, skipped.count), (5, 1), "{input}");
+        assert_eq!(skipped.diagnostics[0].record, Some(2), "{input}");
+    }
+    // The last record is missing its `}`, which leaves the array's `]`
+    let input = r#"[{"lat0":1,"lon0":2,"lat1":3,"lon1":4}, {"lat0":1,"lon0":2,"lat1":3,"lon1":4]"#;
+    assert_eq!(recover(input, ParseOptions::default(), 10).0, 1);
+    // Garbage before the `]`
+    let input = r#"[{"lat0":1,"lon0":2,"lat1":3,"lon1":4} x ]"#;
+    let (read, skipped) = recover(input, ParseOptions::default(), 10);
+    assert_eq!((read, skipped.count), (1, 1));
+}
+
+#[test]
+fn caps_the_record_length() {
+    let long = format!(
+        r#"{{"lat0":1,"lon0":2,"lat1":3,"lon1":4,"pad":"{}"}}"#,
+        "x".repeat(200)
+    );
+    let input = six_records(|_| long.clone());
+    let mut pairs = ArrayIter::<_, CoordPair>::new(input.as_bytes(), ParseOptions::default())
+        .recovering(10)
+        .with_max_record_len(100);
+    assert_eq!(pairs.by_ref().filter(Result::is_ok).count(), 5);
+    let skipped = pairs.skipped().unwrap();
+    assert_eq!(skipped.count, 1);
+    assert_eq!(
+        skipped.diagnostics[0].position.offset,
+        offset_in(&input, &long) + 100
+    );
+}
+
+#[test]
+fn keeps_the_first_few_diagnostics() {
+    let broken = r#"{"lat0":1,"lon0":2,"lat1":3,"lon1":"4"}"#;
+    let input = format!("[{}]", vec![broken; 20].join(","));
+    let (read, skipped) = recover(&input, ParseOptions::default(), 3);
+    assert_eq!((read, skipped.count), (0, 20));
+    let records: Vec<_> = skipped.diagnostics.iter().map(|err| err.record).collect();
+    assert_eq!(records, [Some(0), Some(1), Some(2)]);
+}
+
+#[test]
+fn strict_errors_are_skipped_too() {
+    let input = r#"[{"lat0":1,"lon0":2,"lat1":3,"lon1":4,"lat0":5}, {"lat0":1,"lon0":2,"lat1":3,"lon1":4}]"#;
+    let (read, skipped) = recover(input, ParseOptions::strict(), 10);
+    assert_eq!((read, skipped.count), (1, 1));
+    assert_eq!(
+        skipped.diagnostics[0].kind,
+        ErrorKind::DuplicateMember("lat0".into())
+    );
+}
+
+#[test]
+fn running_out_of_input_still_fails() {
+    for input in [
+        r#"[{"lat0":1,"lon0":2,"lat1":3,"lon1":4}, {"lat0":1"#,
+        r#"[{"lat0":1,"lon0":2,"lat1":3,"lon1":4}, {"lat0":1x} {"#,
+        r#"[{"lat0":1,"lon0":2,"lat1":3,"lon1":4}"#,
+    ] {
+        let mut pairs = ArrayIter::<_, CoordPair>::new(input.as_bytes(), ParseOptions::default())
+            .recovering(10);
+        let err = pairs.find_map(Result::err).unwrap();
+        assert_eq!(err.kind, ErrorKind::TruncatedInput);
+        assert!(pairs.next().is_none());
+    }
+}
+
+#[test]
+fn clean_input_reads_the_same() {
+    let input = generated(50, 1_000);
+    let mut plain = ArrayIter::<_, CoordPair>::new(&input[..], ParseOptions::default());
+    let mut recovering =
+        ArrayIter::<_, CoordPair>::new(&input[..], ParseOptions::default()).recovering(10);
+    assert_eq!((&mut plain).count(), 1_000);
+    assert!(recovering.all(|pair| pair.is_ok()));
+    assert_eq!(plain.stats(), recovering.stats());
+    assert_eq!(recovering.skipped().unwrap().count, 0);
+}
+
+#[test]
+fn ndjson_skips_lines() {
+    let input = "{\"lat0\":1,\"lon0\":2,\"lat1\":3,\"lon1\":4}\n{\"lat0\":1,\n\n{\"lat0\":1,\"lon0\":2,\"lat1\":3,\"lon1\":4} x\n{\"lat0\":5,\"lon0\":6,\"lat1\":7,\"lon1\":8}\n";
+    let mut skipped = Skipped::new(1);
+    let pairs =
+        deserialize_ndjson_recovering(&mut input.as_bytes(), ParseOptions::default(), &mut skipped)
+            .unwrap();
+    assert_eq!((pairs.len(), skipped.count), (2, 2));
+    assert_eq!(skipped.diagnostics.len(), 1);
+    assert_eq!(skipped.diagnostics[0].kind, ErrorKind::TruncatedInput);
+    assert_eq!(skipped.diagnostics[0].record, Some(1));
+}
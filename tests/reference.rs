@@ -0,0 +1,145 @@
+use haversine_calculator::{
+    calc::naive_haversine,
+    parser::{deserialize, looks_like_envelope, ErrorKind, Format, ParseOptions},
+    reference::{deserialize_reference, AnswerChecker, Answers},
+    CoordPair,
+};
+
+mod common;
+use common::{bits, written};
+
+#[test]
+fn x_is_longitude() {
+    // Laid out the way the course's generator writes them
+    let input = b"{\"pairs\":[\n    {\"x0\":10.5, \"y0\":-20, \"x1\":30, \"y1\":40.25},\n    {\"y1\":1, \"x1\":2, \"y0\":3, \"x0\":4}\n]}\n";
+    let expected: Vec<CoordPair> = deserialize(
+        &mut &br#"[{"lat0":-20,"lon0":10.5,"lat1":40.25,"lon1":30},{"lat0":3,"lon0":4,"lat1":1,"lon1":2}]"#[..],
+    )
+    .unwrap();
+    let read = Format::Reference.read_pairs(&mut &input[..]).unwrap();
+    assert_eq!(bits(read), bits(expected));
+}
+
+#[test]
+fn round_trips_through_the_reference_format() {
+    let reference = written(Format::Reference, 46, 5_000);
+    assert!(reference.starts_with(b"{\"pairs\":[{\"x0\":"));
+    assert_eq!(
+        bits(Format::Reference.read_pairs(&mut &reference[..]).unwrap()),
+        bits(
+            Format::Json
+                .read_pairs(&mut &written(Format::Json, 46, 5_000)[..])
+                .unwrap()
+        )
+    );
+}
+
+#[test]
+fn strict_rejects_lat_lon_keys() {
+    let input = br#"{"pairs":[{"x0":1,"y0":2,"x1":3,"y1":4,"lat0":5}]}"#;
+    assert!(deserialize_reference(&mut &input[..], ParseOptions::default()).is_ok());
+    let err = deserialize_reference(&mut &input[..], ParseOptions::strict()).unwrap_err();
+    assert_eq!(err.kind, ErrorKind::UnknownMember("lat0".into()));
+    assert_eq!((err.record, err.position.offset), (Some(0), 40));
+
+    let err = deserialize_reference(
+        &mut &br#"{"pairs":[{"x0":1}]}"#[..],
+        ParseOptions::default(),
+    )
+    .unwrap_err();
+    assert_eq!(err.kind, ErrorKind::MissingMember("y0".into()));
+}
+
+#[test]
+fn detects_formats() {
+    for format in [
+        Format::Json,
+        Format::Ndjson,
+        Format::Csv,
+        Format::Binary,
+        Format::Reference,
+    ] {
+        assert_eq!(Format::detect(&written(format, 46, 10)), Some(format));
+        // Only the first few bytes are looked at
+        assert_eq!(
+            Format::detect(&written(format, 46, 1000)[..64]),
+            Some(format)
+        );
+    }
+    assert_eq!(
+        Format::detect(b"\xEF\xBB\xBF \n{ \"pairs\" : [ {\"y0\": 1"),
+        Some(Format::Reference)
+    );
+    assert_eq!(
+        Format::detect(br#"{"pairs":[{"lat0":1"#),
+        Some(Format::Json)
+    );
+    assert!(looks_like_envelope(br#" { "pairs": [{"lat0":1"#));
+    assert!(!looks_like_envelope(br#"[{"pairs":1}]"#));
+    assert_eq!(
+        Format::detect(b"lon1, lat1 ,lat0,lon0\r\n1,2,3,4"),
+        Some(Format::Csv)
+    );
+    assert_eq!(Format::detect(b""), None);
+    assert_eq!(Format::detect(b"hello"), None);
+}
+
+#[test]
+fn checks_answers() {
+    let pairs = Format::Reference
+        .read_pairs(&mut &written(Format::Reference, 46, 1_000)[..])
+        .unwrap();
+    let distances: Vec<f64> = pairs.into_iter().map(naive_haversine).collect();
+    let average = distances.iter().sum::<f64>() / distances.len() as f64;
+    let mut file = vec![];
+    Answers {
+        distances: distances.clone(),
+        average,
+    }
+    .write(&mut file)
+    .unwrap();
+    assert_eq!(file.len(), (distances.len() + 1) * 8);
+
+    assert_eq!(
+        Answers::read(&mut &file[..]).unwrap(),
+        Answers {
+            distances: distances.clone(),
+            average
+        }
+    );
+    let check = |distances: &[f64], average| {
+        let mut checker = AnswerChecker::new(&file[..]).unwrap();
+        for &distance in distances {
+            checker.add(distance).unwrap();
+        }
+        checker.finish(average)
+    };
+    let passed = check(&distances, average).unwrap();
+    assert!(passed.passed());
+    assert_eq!(passed.checked, 1_000);
+
+    // An ulp off is still a match
+    let mut close = distances.clone();
+    close[3] = f64::from_bits(close[3].to_bits() + 1);
+    assert!(check(&close, average).unwrap().passed());
+
+    let mut off = distances.clone();
+    off[7] += 0.001;
+    off[9] *= 2.0;
+    let failed = check(&off, average + 1e-3).unwrap();
+    assert!(!failed.passed());
+    assert_eq!(failed.mismatches, 2);
+    assert_eq!(failed.first_mismatch, Some((7, off[7], distances[7])));
+    assert!(!failed.average_matches);
+    assert_eq!(failed.average, average);
+
+    // A pair short, and one too many
+    assert!(check(&distances[1..], average).is_err());
+    let mut extra = distances.clone();
+    extra.push(1.0);
+    assert!(check(&extra, average).is_err());
+    assert!(Answers::read(&mut &file[..12]).is_err());
+    assert!(Answers::read(&mut &b""[..]).is_err());
+    assert!(AnswerChecker::new(&file[..12]).is_ok_and(|checker| checker.finish(0.0).is_err()));
+    assert!(AnswerChecker::new(&b""[..]).is_err());
+}
@@ -0,0 +1,114 @@
+use haversine_calculator::{
+    region::{wrap_lon, BoundingBox, Polygon, Region},
+    rng::Xoshiro256StarStar,
+};
+use rand::SeedableRng;
+
+#[test]
+fn wraps_longitudes() {
+    for (lon, wrapped) in [
+        (0.0, 0.0),
+        (179.5, 179.5),
+        (180.0, -180.0),
+        (-180.0, -180.0),
+        (190.0, -170.0),
+        (-190.0, 170.0),
+        (359.0, -1.0),
+        (540.0, -180.0),
+        (-720.0, 0.0),
+    ] {
+        assert_eq!(wrap_lon(lon), wrapped, "{lon}");
+    }
+}
+
+#[test]
+fn boxes_cross_the_antimeridian() {
+    let bbox: BoundingBox = "-10,170,10,-170".parse().unwrap();
+    assert_eq!(bbox.lon_span(), 20.0);
+    for (lat, lon) in [
+        (0.0, 175.0),
+        (0.0, -175.0),
+        (0.0, 180.0),
+        (0.0, -180.0),
+        (10.0, 170.0),
+    ] {
+        assert!(bbox.contains(lat, lon), "{lat},{lon}");
+    }
+    for (lat, lon) in [(0.0, 0.0), (0.0, 169.0), (0.0, -169.0), (11.0, 175.0)] {
+        assert!(!bbox.contains(lat, lon), "{lat},{lon}");
+    }
+
+    let mut rng = Xoshiro256StarStar::seed_from_u64(30);
+    let (mut east, mut west) = (0, 0);
+    for _ in 0..10_000 {
+        let (lat, lon) = bbox.sample(&mut rng);
+        assert!(bbox.contains(lat, lon), "{lat},{lon}");
+        match lon > 0.0 {
+            true => east += 1,
+            false => west += 1,
+        }
+    }
+    // Half the box is on either side
+    assert!((4_500..5_500).contains(&east), "{east} {west}");
+}
+
+#[test]
+fn polygons_use_the_even_odd_rule() {
+    // A pentagram, whose middle is crossed twice and so is outside
+    let star: Polygon = "10,0;-8.09,-5.88;3.09,9.51;3.09,-9.51;-8.09,5.88"
+        .parse()
+        .unwrap();
+    assert!(!star.contains(0.0, 0.0));
+    assert!(star.contains(8.0, 0.0));
+    assert!(star.contains(-7.0, 5.0));
+    assert!(!star.contains(20.0, 0.0));
+
+    // A U shape, with the notch outside
+    let u: Polygon = "0,0;10,0;10,3;2,3;2,7;10,7;10,10;0,10".parse().unwrap();
+    assert!(u.contains(5.0, 1.0));
+    assert!(u.contains(1.0, 5.0));
+    assert!(!u.contains(5.0, 5.0));
+
+    // Stored unwrapped across the antimeridian
+    let strip: Polygon = "0,170;0,-170;10,-170;10,170".parse().unwrap();
+    assert!(strip.contains(5.0, 180.0));
+    assert!(strip.contains(5.0, -175.0));
+    assert!(strip.contains(5.0, 175.0));
+    assert!(!strip.contains(5.0, 0.0));
+}
+
+#[test]
+fn samples_uniformly_by_area() {
+    let region = Region::BoundingBox(BoundingBox::GLOBE);
+    let mut rng = Xoshiro256StarStar::seed_from_u64(31);
+    let mut bands = [0u32; 6];
+    let samples = 120_000;
+    for _ in 0..samples {
+        let (lat, _) = region.sample(&mut rng);
+        bands[(((lat + 90.0) / 30.0) as usize).min(5)] += 1;
+    }
+    // The share of the sphere between two latitudes is half the difference of their sines
+    for (i, &count) in bands.iter().enumerate() {
+        let (low, high) = (-90.0 + 30.0 * i as f64, -60.0 + 30.0 * i as f64);
+        let expected = (high.to_radians().sin() - low.to_radians().sin()) / 2.0;
+        let share = count as f64 / samples as f64;
+        assert!(
+            (share - expected).abs() < 0.01,
+            "band {i}: {share} vs {expected}"
+        );
+    }
+}
+
+#[test]
+fn falls_back_to_the_whole_region() {
+    let bbox: BoundingBox = "0,0,10,10".parse().unwrap();
+    let region = Region::BoundingBox(bbox);
+    let mut rng = Xoshiro256StarStar::seed_from_u64(32);
+    let mut proposed = 0;
+    let (lat, lon) = region.sample_by(&mut rng, |_| {
+        proposed += 1;
+        (-45.0, 90.0)
+    });
+    assert!(region.contains(lat, lon));
+    assert_eq!(proposed, 1000);
+}
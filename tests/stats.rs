@@ -0,0 +1,86 @@
+use std::io::BufReader;
+
+use haversine_calculator::{
+    parser::{deserialize_with_stats, ArrayIter, Deserializable, ParseOptions, ParseStats},
+    CoordPair,
+};
+
+mod common;
+use common::generated;
+
+const INPUT: &[u8] = b" [\n  {\"lat0\":1,\"lon0\":2,\"lat1\":3,\"lon1\":4} ,\n  { \"lon1\" : 4,\n  \"x\": [1, 2], \"lat0\":1,\"lon0\":2,\"lat1\":3, \"y\":\"s\" }\n]\n";
+
+#[test]
+fn counts_records_and_whitespace() {
+    let (pairs, stats) = deserialize_with_stats::<CoordPair>(
+        BufReader::with_capacity(7, INPUT),
+        ParseOptions::default(),
+    )
+    .unwrap();
+    assert_eq!(pairs.len(), 2);
+    assert_eq!(
+        stats,
+        ParseStats {
+            records: 2,
+            // Not the newline after the `]`
+            bytes: INPUT.len() as u64 - 1,
+            min_record_len: 37,
+            max_record_len: 66,
+            record_bytes: 103,
+            // Not the one inside the skipped `[1, 2]` either
+            whitespace_bytes: 19,
+            ignored_keys: 2,
+        }
+    );
+    assert_eq!(stats.mean_record_len(), 51.5);
+}
+
+#[test]
+fn stats_follow_the_iterator() {
+    let mut pairs = ArrayIter::<_, CoordPair>::new(INPUT, ParseOptions::default());
+    assert_eq!(pairs.stats(), ParseStats::default());
+    pairs.next().unwrap().unwrap();
+    let stats = pairs.stats();
+    assert_eq!((stats.records, stats.max_record_len), (1, 37));
+    assert_eq!(pairs.by_ref().count(), 1);
+    assert_eq!(pairs.stats().records, 2);
+
+    let (pairs, stats) =
+        deserialize_with_stats::<CoordPair>(&b"[ ]"[..], ParseOptions::default()).unwrap();
+    assert!(pairs.is_empty());
+    assert_eq!(
+        (stats.records, stats.bytes, stats.whitespace_bytes),
+        (0, 3, 1)
+    );
+    assert_eq!(stats.mean_record_len(), 0.0);
+}
+
+#[derive(Deserializable)]
+struct Track {
+    points: Vec<[f64; 2]>,
+}
+
+#[test]
+fn nested_arrays_are_part_of_the_record() {
+    let input = br#"[{"points": [ [1, 2] , [3,4]], "id": 7}, {"points":[]}]"#;
+    let (tracks, stats) =
+        deserialize_with_stats::<Track>(&input[..], ParseOptions::default()).unwrap();
+    assert_eq!(tracks[0].points, [[1.0, 2.0], [3.0, 4.0]]);
+    assert_eq!(
+        (stats.records, stats.min_record_len, stats.max_record_len),
+        (2, 13, 38)
+    );
+    assert_eq!((stats.whitespace_bytes, stats.ignored_keys), (8, 1));
+}
+
+#[test]
+fn lengths_match_the_generator() {
+    let input = generated(49, 1_000);
+    let (_, stats) =
+        deserialize_with_stats::<CoordPair>(&input[..], ParseOptions::default()).unwrap();
+    assert_eq!(stats.records, 1_000);
+    assert_eq!(stats.bytes, input.len() as u64);
+    // Compact output, a comma between records and the brackets around them
+    assert_eq!(stats.record_bytes, input.len() as u64 - 2 - 999);
+    assert_eq!(stats.whitespace_bytes, 0);
+}
@@ -0,0 +1,113 @@
+use std::io::{BufReader, Read};
+
+use haversine_calculator::{
+    parser::{
+        deserialize, deserialize_iter, deserialize_with_stats, DeserializationError, ParseOptions,
+    },
+    CoordPair,
+};
+
+mod common;
+use common::{bits, generated};
+
+#[test]
+fn iter_matches_deserialize() {
+    let input = generated(42, 10_000);
+    // A small buffer so records straddle refills
+    let pairs: Vec<CoordPair> = deserialize_iter(BufReader::with_capacity(37, &input[..]))
+        .collect::<Result<_, _>>()
+        .unwrap();
+    let expected: Vec<CoordPair> = deserialize(&mut &input[..]).unwrap();
+    assert_eq!(bits(pairs), bits(expected));
+}
+
+#[test]
+fn in_place_and_token_reads_agree() {
+    let input = br#"[ { "lon1" : 4 , "id": 7,"lat0":1,"lon0":2e1,
+  "lat1":-3, "lat0": 5}, {"lat0":1,"note":"a, } b","lon0":2,"lat1":3,"lon1":4} ]"#;
+    // A one byte buffer sends every record through the tokenizer, a slice lets them be read in place
+    let read = |options| {
+        let (pairs, stats) =
+            deserialize_with_stats::<CoordPair>(BufReader::with_capacity(1, &input[..]), options)?;
+        let (in_place, in_place_stats) = deserialize_with_stats::<CoordPair>(&input[..], options)?;
+        assert_eq!((bits(pairs), stats), (bits(in_place), in_place_stats));
+        Ok::<_, DeserializationError>(stats)
+    };
+    let stats = read(ParseOptions::default()).unwrap();
+    assert_eq!((stats.whitespace_bytes, stats.ignored_keys), (14, 2));
+
+    let strict = |input: &[u8]| {
+        let slow = deserialize_with_stats::<CoordPair>(
+            BufReader::with_capacity(1, input),
+            ParseOptions::strict(),
+        );
+        let fast = deserialize_with_stats::<CoordPair>(input, ParseOptions::strict());
+        let (slow, fast) = (slow.unwrap_err(), fast.unwrap_err());
+        assert_eq!(
+            (slow.kind.clone(), slow.position.offset),
+            (fast.kind.clone(), fast.position.offset)
+        );
+    };
+    strict(&input[..]);
+    strict(br#"[{"lat0":1,"lon0":2,"lat1":3}]"#);
+    strict(br#"[{"lat0":1,"lon0":2,"lat1":3,"lon1":4x}]"#);
+}
+
+/// Fails the test if anything reads past `limit` bytes
+struct Limited<'a> {
+    input: &'a [u8],
+    limit: usize,
+}
+
+impl Read for Limited<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        assert!(self.limit > 0, "read past the limit");
+        let len = buf.len().min(self.input.len()).min(self.limit);
+        buf[..len].copy_from_slice(&self.input[..len]);
+        self.input = &self.input[len..];
+        self.limit -= len;
+        Ok(len)
+    }
+}
+
+#[test]
+fn iter_is_lazy() {
+    let input = generated(42, 10_000);
+    let reader = BufReader::with_capacity(
+        64,
+        Limited {
+            input: &input,
+            limit: 1024,
+        },
+    );
+    let first: Vec<CoordPair> = deserialize_iter(reader)
+        .take(3)
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(first.len(), 3);
+}
+
+#[test]
+fn iter_stops_after_the_first_error() {
+    let input = br#"[{"lat0":1,"lon0":2,"lat1":3,"lon1":4},{"lat0":1,"lon0":x,"lat1":3,"lon1":4},{"lat0":1,"lon0":2,"lat1":3,"lon1":4}]"#;
+    let results: Vec<Result<CoordPair, _>> = deserialize_iter(&input[..]).collect();
+    assert_eq!(results.len(), 2);
+    assert!(results[0].is_ok());
+    assert_eq!(results[1].as_ref().unwrap_err().record, Some(1));
+
+    let results: Vec<Result<CoordPair, _>> =
+        deserialize_iter(&br#"[{"lat0":1,"lon0":2,"lat1":3,"lon1":4} x"#[..]).collect();
+    assert_eq!(results.len(), 2);
+    assert!(results[1].is_err());
+}
+
+#[test]
+fn iter_reads_empty_arrays() {
+    for input in ["[]", " [ \n ] "] {
+        assert_eq!(deserialize_iter::<CoordPair>(input.as_bytes()).count(), 0);
+    }
+    assert!(deserialize_iter::<CoordPair>(&b"[,]"[..])
+        .next()
+        .unwrap()
+        .is_err());
+}
@@ -0,0 +1,190 @@
+use std::io::BufReader;
+
+use haversine_calculator::{
+    parser::{
+        deserialize_iter_with, deserialize_ndjson_with, deserialize_parallel_with,
+        deserialize_single_pass_with, deserialize_tree_with, Deserializable, DeserializationError,
+        ErrorKind, ParseOptions,
+    },
+    structural::deserialize_structural_with,
+    tokenizer::Tokenizer,
+    CoordPair,
+};
+
+mod common;
+use common::{bits, compact};
+
+type Parser = fn(&str, ParseOptions) -> Result<Vec<CoordPair>, DeserializationError>;
+
+// Every JSON array parser, by name for the failure messages
+const PARSERS: [(&str, Parser); 5] = [
+    ("streaming", |input, options| {
+        deserialize_iter_with(input.as_bytes(), options).collect()
+    }),
+    ("single pass", deserialize_single_pass_with),
+    ("parallel", |input, options| {
+        deserialize_parallel_with(input, 4, options)
+    }),
+    ("tree", |input, options| {
+        deserialize_tree_with(input.as_bytes(), options)
+    }),
+    ("structural", |input, options| {
+        deserialize_structural_with(input.as_bytes(), options)
+    }),
+];
+
+fn strict_error(parser: Parser, input: &str) -> DeserializationError {
+    parser(input, ParseOptions::strict()).unwrap_err()
+}
+
+#[test]
+fn strict_reads_clean_input_like_lenient() {
+    let input = compact(45, 10_000);
+    for (name, parser) in PARSERS {
+        assert_eq!(
+            bits(parser(&input, ParseOptions::strict()).unwrap()),
+            bits(parser(&input, ParseOptions::default()).unwrap()),
+            "{name}"
+        );
+    }
+}
+
+#[test]
+fn lenient_skips_unknown_and_keeps_the_last_duplicate() {
+    let input = r#"[{"lat0":1,"alt":9,"lon0":2,"lat1":3,"lon1":4,"lat0":5}]"#;
+    let expected = bits(
+        deserialize_tree_with(
+            br#"[{"lat0":5,"lon0":2,"lat1":3,"lon1":4}]"#,
+            ParseOptions::default(),
+        )
+        .unwrap(),
+    );
+    for (name, parser) in PARSERS {
+        assert_eq!(
+            bits(parser(input, ParseOptions::default()).unwrap()),
+            expected,
+            "{name}"
+        );
+    }
+}
+
+#[test]
+fn rejects_unknown_members_at_the_key() {
+    let input = "[{\"lat0\":1,\"lon0\":2,\"lat1\":3,\"lon1\":4},\n {\"lat0\":1, \"alt\":9,\"lon0\":2,\"lat1\":3,\"lon1\":4}]";
+    for (name, parser) in PARSERS {
+        let err = strict_error(parser, input);
+        assert_eq!(err.kind, ErrorKind::UnknownMember("alt".into()), "{name}");
+        assert_eq!(err.record, Some(1), "{name}");
+        // The tree doesn't keep offsets below the record
+        if name != "tree" {
+            assert_eq!((err.position.line, err.position.column), (2, 14), "{name}");
+        }
+    }
+}
+
+#[test]
+fn rejects_duplicate_members_at_the_repeat() {
+    let input = r#"[{"lat0":1,"lon0":2,"lat1":3,"lon1":4,"lon0":2}]"#;
+    for (name, parser) in PARSERS {
+        let err = strict_error(parser, input);
+        assert_eq!(
+            err.kind,
+            ErrorKind::DuplicateMember("lon0".into()),
+            "{name}"
+        );
+        assert_eq!(err.record, Some(0), "{name}");
+        if name != "tree" {
+            assert_eq!(err.position.offset, 39, "{name}");
+        }
+    }
+}
+
+#[test]
+fn rejects_values_that_arent_plain_numbers() {
+    for input in [
+        r#"[{"lat0":"1","lon0":2,"lat1":3,"lon1":4}]"#,
+        r#"[{"lat0":{"deg":1},"lon0":2,"lat1":3,"lon1":4}]"#,
+        r#"[{"lat0":[1],"lon0":2,"lat1":3,"lon1":4}]"#,
+        r#"[{"lat0":true,"lon0":2,"lat1":3,"lon1":4}]"#,
+        r#"[{"lat0":null,"lon0":2,"lat1":3,"lon1":4}]"#,
+    ] {
+        for (name, parser) in PARSERS {
+            assert!(
+                parser(input, ParseOptions::strict()).is_err(),
+                "{name} {input}"
+            );
+        }
+    }
+    // Only the tree reads quoted numbers when lenient
+    let quoted = br#"[{"lat0":"1","lon0":2,"lat1":3,"lon1":4}]"#;
+    assert!(deserialize_tree_with(quoted, ParseOptions::default()).is_ok());
+}
+
+#[test]
+fn rejects_numbers_json_doesnt_spell() {
+    let record = |lat1: &str| format!(r#"{{"lat0":1,"lon0":2,"lat1":{lat1},"lon1":4}}"#);
+    for spelling in [
+        "NaN", "inf", "-inf", "Infinity", "+1", ".5", "-.5", "1.", "01", "-01", "1e", "1.5e+",
+        "0x10",
+    ] {
+        let input = format!("[{}, {}]", record("3"), record(spelling));
+        let offset = input.rfind(spelling).unwrap() as u64;
+        for (name, parser) in PARSERS {
+            let err = strict_error(parser, &input);
+            // The tree rejects them while it parses the document, before there are records
+            if name != "tree" {
+                assert_eq!(
+                    (err.kind, err.record, err.position.offset),
+                    (ErrorKind::BadNumber, Some(1), offset),
+                    "{name} {spelling}"
+                );
+            }
+        }
+        // A one byte buffer reads every value through the tokenizer instead of in place
+        let err = deserialize_iter_with::<CoordPair>(
+            BufReader::with_capacity(1, input.as_bytes()),
+            ParseOptions::strict(),
+        )
+        .find_map(Result::err)
+        .unwrap();
+        assert_eq!(
+            (err.kind, err.position.offset),
+            (ErrorKind::BadNumber, offset),
+            "{spelling}"
+        );
+        let mut tokens = Tokenizer::new(spelling.as_bytes()).with_options(ParseOptions::strict());
+        assert!(
+            f32::tokenized_deserialize(&mut tokens).is_err(),
+            "{spelling}"
+        );
+    }
+
+    let input = format!(
+        "[{}]",
+        ["-0", "0.5e-3", "1E+2", "-12.25", "0e0"]
+            .map(record)
+            .join(",")
+    );
+    for (name, parser) in PARSERS {
+        assert_eq!(
+            bits(parser(&input, ParseOptions::strict()).unwrap()),
+            bits(parser(&input, ParseOptions::default()).unwrap()),
+            "{name}"
+        );
+    }
+}
+
+#[test]
+fn ndjson_is_strict_per_line() {
+    let input = b"{\"lat0\":1,\"lon0\":2,\"lat1\":3,\"lon1\":4}\n{\"lat0\":1,\"lon0\":2,\"lat1\":3,\"lon1\":4,\"lat1\":3}\n";
+    assert_eq!(
+        deserialize_ndjson_with(&mut &input[..], ParseOptions::default())
+            .unwrap()
+            .len(),
+        2
+    );
+    let err = deserialize_ndjson_with(&mut &input[..], ParseOptions::strict()).unwrap_err();
+    assert_eq!(err.kind, ErrorKind::DuplicateMember("lat1".into()));
+    assert_eq!(err.record, Some(1));
+    assert_eq!((err.position.line, err.position.column), (2, 39));
+}
@@ -0,0 +1,104 @@
+use haversine_calculator::{
+    generate::{ClusterShape, Concentration, CoordPairGen},
+    messy::MessyJson,
+    parser::{deserialize_single_pass, deserialize_tree},
+    rng::Xoshiro256StarStar,
+    structural::{deserialize_structural, StructuralIndex},
+    track::{TrackGen, TrackParams},
+};
+use rand::{RngCore, SeedableRng};
+
+mod common;
+use common::{bits, json};
+
+fn assert_matches_single_pass(input: &[u8]) {
+    let expected = deserialize_single_pass(std::str::from_utf8(input).unwrap()).unwrap();
+    assert_eq!(bits(deserialize_structural(input).unwrap()), bits(expected));
+}
+
+#[test]
+fn matches_single_pass_on_generated_files() {
+    assert_matches_single_pass(&json(CoordPairGen::from_seed(1212121212, false, 20_000)));
+    assert_matches_single_pass(&json(CoordPairGen::from_seed(1212121212, true, 20_000)));
+    for shape in [ClusterShape::Gaussian, ClusterShape::VonMisesFisher] {
+        let gen = CoordPairGen::from_seed(7, true, 20_000)
+            .with_cluster_shape(shape, Concentration::new(50.0).unwrap());
+        assert_matches_single_pass(&json(gen));
+    }
+    let params = TrackParams {
+        speed: 13.9,
+        heading_drift: 5.0,
+        interval: 1.0,
+        gps_noise: 3.0,
+        track_len: 1000,
+    };
+    assert_matches_single_pass(&json(TrackGen::from_seed(3, params).segments(20_000)));
+    assert!(deserialize_structural(b"[]").unwrap().is_empty());
+}
+
+#[test]
+fn matches_tree_on_messy_files() {
+    for (seed, extra_keys) in [(1, false), (2, true), (3, true)] {
+        let mut input = vec![];
+        MessyJson::new(Xoshiro256StarStar::seed_from_u64(seed), extra_keys)
+            .write_pairs(CoordPairGen::from_seed(seed, true, 5_000), &mut input)
+            .unwrap();
+        assert_eq!(
+            bits(deserialize_structural(&input).unwrap()),
+            bits(deserialize_tree(&input).unwrap())
+        );
+    }
+}
+
+#[test]
+fn skips_strings_and_nested_values() {
+    // Escaped quotes and backslashes, and structural characters inside strings, at every offset in a block
+    for pad in 0..70 {
+        let input = format!(
+            r#"[{{"lat0":1,"{}":"a\"b\\","lon0":2,"x":{{"y":["}}]",[{{}}]]}},"lat1":3,"lon1":4,"z":"\\\\\"}}"}}]"#,
+            "p".repeat(pad)
+        );
+        assert_eq!(
+            bits(deserialize_structural(input.as_bytes()).unwrap()),
+            bits(deserialize_tree(input.as_bytes()).unwrap()),
+            "{input}"
+        );
+    }
+}
+
+#[test]
+fn simd_index_matches_scalar() {
+    let mut rng = Xoshiro256StarStar::seed_from_u64(38);
+    let alphabet = b"{}[]:,\"\\ a1";
+    for len in 0..300 {
+        let input: Vec<u8> = (0..len)
+            .map(|_| alphabet[rng.next_u64() as usize % alphabet.len()])
+            .collect();
+        assert_eq!(
+            StructuralIndex::new(&input),
+            StructuralIndex::scalar(&input)
+        );
+    }
+}
+
+#[test]
+fn rejects_malformed_input() {
+    for input in [
+        &b""[..],
+        b"[",
+        b"[{\"lat0\":1,\"lon0\":2,\"lat1\":3}]",
+        b"[{\"lat0\":1,\"lon0\":2,\"lat1\":3,\"lon1\":x}]",
+        b"[{\"lat0\":1,\"lon0\":2,\"lat1\":3,\"lon1\":4}",
+        b"[{\"lat0\":1,\"lon0\":2,\"lat1\":3,\"lon1\":\"4\"}]",
+        b"[{\"lat0\":1,\"lon0\":2,\"lat1\":3,\"lon1\":4}]x",
+        b"[{\"lat0\":1,\"lon0\":2,\"lat1\":3,\"lon1\":4,}]",
+        b"[{\"lat0\" 1,\"lon0\":2,\"lat1\":3,\"lon1\":4}]",
+        b"[{\"lat0\":1,\"lon0\":2,\"lat1\":3,\"lon1\":4} {}]",
+    ] {
+        assert!(
+            deserialize_structural(input).is_err(),
+            "{}",
+            input.escape_ascii()
+        );
+    }
+}
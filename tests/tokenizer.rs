@@ -0,0 +1,83 @@
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    cell::Cell,
+    io::BufReader,
+};
+
+use haversine_calculator::tokenizer::Tokenizer;
+
+/// Counts the allocations made on each thread, so tests running alongside don't get in the way
+struct Counting;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.with(|count| count.set(count.get() + 1));
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.with(|count| count.set(count.get() + 1));
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: Counting = Counting;
+
+fn allocations() -> usize {
+    ALLOCATIONS.with(Cell::get)
+}
+
+#[test]
+fn spans_straddle_refills() {
+    let input = br#"[ 12345.5 , "ab\"cd" ,{"x": [1, "]"]} , true ]"#;
+    // Every window size from a byte up cuts the spans in different places
+    for capacity in 1..=input.len() {
+        let mut tokens = Tokenizer::new(BufReader::with_capacity(capacity, &input[..]));
+        let mut first = true;
+        tokens.expect(b'[', "Expected '['").unwrap();
+        let mut read = vec![];
+        while tokens.has_element(&mut first).unwrap() {
+            let span = match tokens.skip_whitespace().unwrap() {
+                Some(b'"') => tokens.with_string(|raw| Ok(raw.to_vec())),
+                Some(b'{') => tokens.with_value(|raw| Ok(raw.to_vec())),
+                _ => tokens.with_token(|raw| Ok(raw.to_vec())),
+            };
+            read.push(String::from_utf8(span.unwrap()).unwrap());
+        }
+        assert_eq!(
+            read,
+            ["12345.5", r#"ab\"cd"#, r#"{"x": [1, "]"]}"#, "true"],
+            "{capacity}"
+        );
+        assert_eq!(tokens.position().offset, input.len() as u64 - 1);
+    }
+}
+
+#[test]
+fn reuses_its_scratch_buffer() {
+    let input = format!("[{}1]", "123456.789,".repeat(1_000));
+    // Every token is longer than the window, so each one is copied into the scratch buffer
+    let mut tokens = Tokenizer::new(BufReader::with_capacity(4, input.as_bytes()));
+    let mut first = true;
+    tokens.expect(b'[', "Expected '['").unwrap();
+    let token_len = |tokens: &mut Tokenizer<_>| tokens.with_token(|raw| Ok(raw.len()));
+    assert!(tokens.has_element(&mut first).unwrap());
+    assert_eq!(token_len(&mut tokens).unwrap(), 10);
+
+    let before = allocations();
+    let mut lengths = 0;
+    while tokens.has_element(&mut first).unwrap() {
+        lengths += token_len(&mut tokens).unwrap();
+    }
+    assert_eq!(allocations(), before);
+    assert_eq!(lengths, 999 * 10 + 1);
+}
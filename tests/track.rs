@@ -0,0 +1,93 @@
+use haversine_calculator::{
+    calc::naive_haversine,
+    track::{write_tracks_json, TrackGen, TrackParams},
+    CoordPair,
+};
+
+mod common;
+use common::coord_bits;
+
+fn params(gps_noise: f64, track_len: usize) -> TrackParams {
+    TrackParams {
+        speed: 20.0,
+        heading_drift: 10.0,
+        interval: 5.0,
+        gps_noise,
+        track_len,
+    }
+}
+
+#[test]
+fn segments_join_up_within_a_track() {
+    let track_len = 50;
+    let segments: Vec<CoordPair> = TrackGen::from_seed(29, params(3.0, track_len))
+        .segments(1_000)
+        .collect();
+    assert_eq!(segments.len(), 1_000);
+    let bits = coord_bits(segments);
+    let mut breaks = 0;
+    for (i, pair) in bits
+        .chunks_exact(4)
+        .zip(bits.chunks_exact(4).skip(1))
+        .enumerate()
+    {
+        let (end, start) = (&pair.0[2..], &pair.1[..2]);
+        // Each track of 50 points gives 49 segments, then the next starts somewhere else
+        if (i + 1) % (track_len - 1) == 0 {
+            assert_ne!(end, start, "segment {i}");
+            breaks += 1;
+        } else {
+            assert_eq!(end, start, "segment {i}");
+        }
+    }
+    assert_eq!(breaks, 999 / (track_len - 1));
+}
+
+fn mean_and_spread(values: &[f64]) -> (f64, f64) {
+    let mean = values.iter().sum::<f64>() / values.len() as f64;
+    let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / values.len() as f64;
+    (mean, variance.sqrt())
+}
+
+#[test]
+fn steps_cover_the_distance() {
+    let step = 20.0 * 5.0 / 1000.0;
+    let distances: Vec<f64> = TrackGen::from_seed(29, params(0.0, 1_000))
+        .segments(10_000)
+        .map(naive_haversine)
+        .collect();
+    // The speed is jittered by 10%, so every step stays well within half of it
+    assert!(distances.iter().all(|&d| d > step * 0.5 && d < step * 1.5));
+    let (mean, spread) = mean_and_spread(&distances);
+    assert!((mean - step).abs() < step * 0.01, "{mean}");
+
+    // GPS noise spreads the steps out but barely moves them on average
+    let noisy: Vec<f64> = TrackGen::from_seed(29, params(10.0, 1_000))
+        .segments(10_000)
+        .map(naive_haversine)
+        .collect();
+    let (noisy_mean, noisy_spread) = mean_and_spread(&noisy);
+    assert!((noisy_mean - step).abs() < step * 0.03, "{noisy_mean}");
+    assert!(noisy_spread > spread * 1.3, "{noisy_spread} {spread}");
+}
+
+#[test]
+fn points_are_grouped_by_track() {
+    let points: Vec<_> = TrackGen::from_seed(29, params(3.0, 4)).take(10).collect();
+    let tracks: Vec<_> = points.iter().map(|point| point.track).collect();
+    assert_eq!(tracks, [0, 0, 0, 0, 1, 1, 1, 1, 2, 2]);
+    assert!(points
+        .iter()
+        .all(|point| point.lat.abs() <= 90.0 && point.lon.abs() <= 180.0));
+
+    let mut out = vec![];
+    write_tracks_json(points.into_iter(), &mut out).unwrap();
+    let out = String::from_utf8(out).unwrap();
+    assert!(out.starts_with("[[{\"lat\":") && out.ends_with("}]]"));
+    assert_eq!(out.matches("],[").count(), 2);
+    assert_eq!(out.matches("\"lon\"").count(), 10);
+
+    let mut out = vec![];
+    write_tracks_json(std::iter::empty(), &mut out).unwrap();
+    assert_eq!(out, b"[]");
+}
@@ -0,0 +1,181 @@
+use std::{fmt::Debug, io::Write};
+
+use haversine_calculator::{
+    parser::{deserialize, serialize, Deserializable, ErrorKind, Serializable},
+    pretty::{serialize_pretty, PrettyConfig, PrettyWriter},
+};
+
+#[derive(Debug, PartialEq, Serializable, Deserializable)]
+struct Report {
+    name: String,
+    runs: Vec<(u32, f64)>,
+    best: Option<[f32; 2]>,
+    notes: Vec<Option<String>>,
+    empty: Vec<bool>,
+}
+
+fn report() -> Report {
+    Report {
+        name: "warm \"cache\"".to_string(),
+        runs: vec![(1, 0.5), (2, 1e-7)],
+        best: Some([1.5, -0.1]),
+        notes: vec![None, Some("a\nb".to_string())],
+        empty: vec![],
+    }
+}
+
+fn to_json<S: Serializable + ?Sized>(value: &mut S) -> String {
+    let mut out = vec![];
+    serialize(value, &mut out).unwrap();
+    String::from_utf8(out).unwrap()
+}
+
+fn pretty<S: Serializable + ?Sized>(value: &mut S, config: PrettyConfig) -> String {
+    let mut out = vec![];
+    serialize_pretty(value, &mut out, config).unwrap();
+    String::from_utf8(out).unwrap()
+}
+
+fn round_trip<T: Serializable + Deserializable + PartialEq + Debug>(mut value: T, json: &str) {
+    assert_eq!(to_json(&mut value), json);
+    let read: T = deserialize(&mut json.as_bytes()).unwrap();
+    assert_eq!(read, value);
+}
+
+#[test]
+fn writes_and_reads_standard_types() {
+    round_trip(0.1f32, "0.1");
+    round_trip(16_777_217f64, "16777217.0");
+    round_trip(-128i8, "-128");
+    round_trip(u128::MAX, "340282366920938463463374607431768211455");
+    round_trip(false, "false");
+    round_trip("tab\there".to_string(), r#""tab\there""#);
+    round_trip(None::<u8>, "null");
+    round_trip(Some(Some(3u8)), "3");
+    round_trip(vec![1u8, 2, 3], "[1,2,3]");
+    round_trip(Vec::<u8>::new(), "[]");
+    round_trip([[1u8, 2], [3, 4]], "[[1,2],[3,4]]");
+    round_trip([0u8; 0], "[]");
+    round_trip((1u8,), "[1]");
+    round_trip(
+        (
+            1u8,
+            -2i64,
+            0.5f64,
+            true,
+            "s".to_string(),
+            None::<u8>,
+            [9u8],
+            (7u8, 8u8),
+        ),
+        r#"[1,-2,0.5,true,"s",null,[9],[7,8]]"#,
+    );
+    round_trip(
+        report(),
+        r#"{"name":"warm \"cache\"","runs":[[1,0.5],[2,1e-7]],"best":[1.5,-0.1],"notes":[null,"a\nb"],"empty":[]}"#,
+    );
+}
+
+#[test]
+fn writes_slices_and_strs() {
+    assert_eq!(to_json(&mut [1.5f64, 2.0][..]), "[1.5,2.0]");
+    let mut s = "caf\u{e9} \u{7}".to_string();
+    assert_eq!(to_json(s.as_mut_str()), r#""café \u0007""#);
+}
+
+#[test]
+fn reads_any_layout() {
+    let read: (Vec<Option<u8>>, [bool; 2]) =
+        deserialize(&mut &b" [ [ null , 1 ] ,\n [ true,false ] ] "[..]).unwrap();
+    assert_eq!(read, (vec![None, Some(1)], [true, false]));
+    let read: f32 = deserialize(&mut &b"3.4028235e38"[..]).unwrap();
+    assert_eq!(read, f32::MAX);
+}
+
+#[test]
+fn rejects_the_wrong_shape() {
+    let err = deserialize::<[u8; 3]>(&mut &b"[1, 2]"[..]).unwrap_err();
+    assert_eq!(err.kind, ErrorKind::UnexpectedByte(b']'));
+    assert_eq!(err.position.offset, 5);
+    let err = deserialize::<(u8, u8)>(&mut &b"[1,2,3]"[..]).unwrap_err();
+    assert_eq!(err.kind, ErrorKind::UnexpectedByte(b'3'));
+    let err = deserialize::<Option<u8>>(&mut &b"nil"[..]).unwrap_err();
+    assert_eq!(err.kind, ErrorKind::UnexpectedByte(b'n'));
+    let err = deserialize::<f32>(&mut &b"1e"[..]).unwrap_err();
+    assert_eq!(err.kind, ErrorKind::BadNumber);
+    let err = deserialize::<[u8; 1]>(&mut &b"[1,]"[..]).unwrap_err();
+    assert_eq!(err.kind, ErrorKind::UnexpectedByte(b']'));
+}
+
+#[test]
+fn pretty_prints() {
+    assert_eq!(
+        pretty(&mut report(), PrettyConfig::default()),
+        r#"{
+  "name": "warm \"cache\"",
+  "runs": [
+    [
+      1,
+      0.5
+    ],
+    [
+      2,
+      1e-7
+    ]
+  ],
+  "best": [
+    1.5,
+    -0.1
+  ],
+  "notes": [
+    null,
+    "a\nb"
+  ],
+  "empty": []
+}"#
+    );
+    let config = PrettyConfig {
+        indent: "\t".to_string(),
+        newline: "\r\n".to_string(),
+        spaces: false,
+        expand_depth: 2,
+    };
+    assert_eq!(
+        pretty(&mut report(), config),
+        "{\r\n\t\"name\":\"warm \\\"cache\\\"\",\r\n\t\"runs\":[\r\n\t\t[1,0.5],\r\n\t\t[2,1e-7]\r\n\t],\r\n\t\"best\":[\r\n\t\t1.5,\r\n\t\t-0.1\r\n\t],\r\n\t\"notes\":[\r\n\t\tnull,\r\n\t\t\"a\\nb\"\r\n\t],\r\n\t\"empty\":[]\r\n}"
+    );
+    let one_line = PrettyConfig {
+        expand_depth: 0,
+        ..PrettyConfig::default()
+    };
+    assert_eq!(
+        pretty(&mut vec![(1u8, "a, b: [c]".to_string())], one_line),
+        r#"[[1, "a, b: [c]"]]"#
+    );
+    assert_eq!(pretty(&mut 5u8, PrettyConfig::default()), "5");
+}
+
+#[test]
+fn rejects_non_finite_floats() {
+    for value in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+        let err = serialize(&mut vec![1.0, value], &mut vec![]).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+        assert!(serialize(&mut (value as f32), &mut vec![]).is_err());
+    }
+    assert_eq!(to_json(&mut [f64::MAX]), "[1.7976931348623157e308]");
+    assert_eq!(to_json(&mut -0.0f32), "-0.0");
+}
+
+#[test]
+fn pretty_writer_takes_stray_brackets() {
+    let mut writer = PrettyWriter::new(vec![], PrettyConfig::default());
+    writer.write_all(b"]}[1]]").unwrap();
+    assert_eq!(writer.into_inner(), b"\n]\n}[\n  1\n]\n]");
+}
+
+#[test]
+fn pretty_output_reads_back() {
+    let json = pretty(&mut report(), PrettyConfig::default());
+    let read: Report = deserialize(&mut json.as_bytes()).unwrap();
+    assert_eq!(read, report());
+}